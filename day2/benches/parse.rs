@@ -0,0 +1,39 @@
+#![feature(test)]
+
+extern crate test;
+
+use day2::generator::{Generator, GeneratorConfig};
+use day2::parser::{self, GameReader};
+use day2::{BLUE, GREEN, RED};
+use test::Bencher;
+
+fn input() -> String {
+    let config = GeneratorConfig {
+        games: 10_000,
+        ..GeneratorConfig::default()
+    };
+    Generator::new(config, 2023).unwrap().input()
+}
+
+#[bench]
+fn parse_games(b: &mut Bencher) {
+    let input = input();
+    b.bytes = input.len() as u64;
+    b.iter(|| parser::parse(&input).unwrap());
+}
+
+#[bench]
+fn read_games(b: &mut Bencher) {
+    let input = input();
+    b.bytes = input.len() as u64;
+    b.iter(|| {
+        GameReader::new(input.as_bytes())
+            .map(|game| {
+                game.unwrap()
+                    .bag_state()
+                    .power_over([RED, GREEN, BLUE])
+                    .unwrap()
+            })
+            .sum::<u64>()
+    });
+}
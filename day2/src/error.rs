@@ -0,0 +1,142 @@
+use std::fmt::Display;
+
+use nom::error::ErrorKind;
+use thiserror::Error;
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ParseError {
+    #[error("line {line}, column {column}: expected {expected}\n    {text}")]
+    Line {
+        line: usize,
+        column: usize,
+        text: String,
+        expected: Expected,
+    },
+    #[error("line {line}, column {column}: {source}\n    {text}")]
+    Bag {
+        line: usize,
+        column: usize,
+        text: String,
+        source: BagError,
+    },
+}
+
+#[derive(Debug, Error)]
+pub enum ReadError {
+    #[error("failed to read games")]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum BagError {
+    #[error("cube count overflowed")]
+    Overflow,
+    #[error("cube count went below zero")]
+    Underflow,
+    #[error("color {color:?} is not a non-empty run of ASCII lowercase letters")]
+    InvalidColor { color: String },
+    #[error("a pull needs at least one cube")]
+    EmptyPull,
+    #[error("a game needs at least one pull")]
+    EmptyGame,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("can't parse query {query:?} at column {column}")]
+pub struct QueryError {
+    pub column: usize,
+    pub query: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum GeneratorError {
+    #[error("no colors to generate cubes from")]
+    NoColors,
+    #[error("color {color:?} is not a non-empty run of ASCII lowercase letters")]
+    InvalidColor { color: String },
+    #[error("color {color:?} is listed more than once")]
+    DuplicateColor { color: String },
+    #[error("every game needs at least one pull")]
+    NoPulls,
+    #[error("every pull needs at least one cube")]
+    EmptyPulls,
+    #[error("no game can meet the constraint")]
+    Unsatisfiable,
+}
+
+/// An irregularity the lenient parser recovered from. Columns count from 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Warning {
+    Whitespace {
+        column: usize,
+    },
+    GameTag {
+        column: usize,
+        found: String,
+    },
+    UppercaseColor {
+        column: usize,
+        found: String,
+    },
+    PluralColor {
+        column: usize,
+        found: String,
+    },
+    TrailingSeparator {
+        column: usize,
+    },
+    /// Blank lines skipped before this game, starting at `line`.
+    BlankLines {
+        line: usize,
+        count: usize,
+    },
+}
+
+impl Display for Warning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Whitespace { column } => write!(f, "column {}: irregular whitespace", column),
+            Self::GameTag { column, found } => {
+                write!(f, "column {}: {:?} should be \"Game\"", column, found)
+            }
+            Self::UppercaseColor { column, found } => {
+                write!(f, "column {}: color {:?} is not lowercase", column, found)
+            }
+            Self::PluralColor { column, found } => {
+                write!(f, "column {}: color {:?} is plural", column, found)
+            }
+            Self::TrailingSeparator { column } => {
+                write!(f, "column {}: separator with nothing after it", column)
+            }
+            Self::BlankLines { line, count } => {
+                write!(f, "{} blank line(s) skipped from line {}", count, line)
+            }
+        }
+    }
+}
+
+/// What the parser was looking for when it gave up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Expected {
+    Tag(&'static str),
+    Number,
+    NumberInRange,
+    Color,
+    EndOfPull,
+    Other(ErrorKind),
+}
+
+impl Display for Expected {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Tag(tag) => write!(f, "{:?}", tag),
+            Self::Number => write!(f, "a number"),
+            Self::NumberInRange => write!(f, "a number no larger than {}", u32::MAX),
+            Self::Color => write!(f, "a color (a lowercase word)"),
+            Self::EndOfPull => write!(f, "\", \", \"; \" or the end of the line"),
+            Self::Other(kind) => write!(f, "{}", kind.description()),
+        }
+    }
+}
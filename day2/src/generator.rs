@@ -0,0 +1,349 @@
+//! Seeded generation of random games, for fuzzing the parser and benchmarking.
+
+use std::ops::RangeInclusive;
+
+use crate::{error::GeneratorError, is_color, BagState, Cube, Game, Pull};
+
+/// SplitMix64. Small and good enough for test data; not for anything that needs real randomness.
+#[derive(Debug, Clone)]
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// A value in `0..n`. `n` must not be zero.
+    pub fn below(&mut self, n: u64) -> u64 {
+        self.next_u64() % n
+    }
+
+    pub fn in_range(&mut self, range: &RangeInclusive<u32>) -> u32 {
+        let span = (*range.end() as u64).saturating_sub(*range.start() as u64) + 1;
+        range.start() + self.below(span) as u32
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Constraint {
+    /// Every game is possible with this bag.
+    Feasible(BagState),
+    /// No game is possible with this bag.
+    Infeasible(BagState),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GeneratorConfig {
+    pub games: usize,
+    pub pulls_per_game: RangeInclusive<u32>,
+    pub cubes_per_pull: RangeInclusive<u32>,
+    pub colors: Vec<String>,
+    pub constraint: Option<Constraint>,
+}
+
+impl Default for GeneratorConfig {
+    fn default() -> Self {
+        Self {
+            games: 100,
+            pulls_per_game: 1..=6,
+            cubes_per_pull: 1..=30,
+            colors: vec![
+                crate::RED.to_string(),
+                crate::GREEN.to_string(),
+                crate::BLUE.to_string(),
+            ],
+            constraint: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Generator {
+    config: GeneratorConfig,
+    rng: Rng,
+}
+
+impl Generator {
+    pub fn new(config: GeneratorConfig, seed: u64) -> Result<Self, GeneratorError> {
+        if config.colors.is_empty() {
+            return Err(GeneratorError::NoColors);
+        }
+        // Only colors the parser accepts, so that the input round-trips.
+        if let Some(color) = config.colors.iter().find(|color| !is_color(color)) {
+            return Err(GeneratorError::InvalidColor {
+                color: color.clone(),
+            });
+        }
+        // Limits are kept per entry, so a repeated color could exceed its limit.
+        if let Some((_, color)) = config
+            .colors
+            .iter()
+            .enumerate()
+            .find(|(idx, color)| config.colors[..*idx].contains(color))
+        {
+            return Err(GeneratorError::DuplicateColor {
+                color: color.clone(),
+            });
+        }
+        if config.pulls_per_game.is_empty() || *config.pulls_per_game.start() == 0 {
+            return Err(GeneratorError::NoPulls);
+        }
+        if config.cubes_per_pull.is_empty() || *config.cubes_per_pull.start() == 0 {
+            return Err(GeneratorError::EmptyPulls);
+        }
+        match &config.constraint {
+            Some(Constraint::Feasible(bag))
+                if config.colors.iter().all(|color| bag.count(color) == 0) =>
+            {
+                return Err(GeneratorError::Unsatisfiable);
+            }
+            Some(Constraint::Infeasible(bag))
+                if config
+                    .colors
+                    .iter()
+                    .all(|color| bag.count(color) == u32::MAX) =>
+            {
+                return Err(GeneratorError::Unsatisfiable);
+            }
+            _ => {}
+        }
+        Ok(Self {
+            config,
+            rng: Rng::new(seed),
+        })
+    }
+
+    /// Splits `total` cubes at random between the colors, never giving a color more than its
+    /// entry in `limits`.
+    fn pull(&mut self, total: u32, limits: &[u32]) -> Pull {
+        let mut counts = vec![0u32; self.config.colors.len()];
+        let mut order = Vec::new();
+        for _ in 0..total {
+            let open = (0..counts.len())
+                .filter(|&idx| counts[idx] < limits[idx])
+                .collect::<Vec<_>>();
+            if open.is_empty() {
+                break;
+            }
+            let idx = open[self.rng.below(open.len() as u64) as usize];
+            if counts[idx] == 0 {
+                order.push(idx);
+            }
+            counts[idx] += 1;
+        }
+        let cubes = order
+            .into_iter()
+            .map(|idx| Cube::new(&self.config.colors[idx], counts[idx]))
+            .collect();
+        Pull::new(cubes).expect("a pull never holds more than u32::MAX cubes of one color")
+    }
+
+    pub fn game(&mut self, id: u32) -> Game {
+        let pull_count = self.rng.in_range(&self.config.pulls_per_game);
+        let limits = match &self.config.constraint {
+            Some(Constraint::Feasible(bag)) => self
+                .config
+                .colors
+                .iter()
+                .map(|color| bag.count(color))
+                .collect(),
+            _ => vec![u32::MAX; self.config.colors.len()],
+        };
+        let mut pulls = (0..pull_count)
+            .map(|_| {
+                let total = self.rng.in_range(&self.config.cubes_per_pull);
+                self.pull(total, &limits)
+            })
+            .collect::<Vec<_>>();
+        if let Some(Constraint::Infeasible(bag)) = &self.config.constraint {
+            // Overfill one color in one pull so the game can't come out of `bag`.
+            let colors = self
+                .config
+                .colors
+                .iter()
+                .filter(|color| bag.count(color) < u32::MAX)
+                .collect::<Vec<_>>();
+            let color = colors[self.rng.below(colors.len() as u64) as usize];
+            let count = bag.count(color) + 1;
+            let idx = self.rng.below(pulls.len() as u64) as usize;
+            let mut cubes = pulls[idx]
+                .cubes()
+                .iter()
+                .filter(|cube| &cube.color != color)
+                .cloned()
+                .collect::<Vec<_>>();
+            let position = self.rng.below(cubes.len() as u64 + 1) as usize;
+            cubes.insert(position, Cube::new(color, count));
+            pulls[idx] = Pull::new(cubes).expect("only one color was changed");
+        }
+        Game::new(id, pulls).expect("every game has at least one pull")
+    }
+
+    /// Games numbered from 1, as many as the config asks for.
+    pub fn games(&mut self) -> Vec<Game> {
+        (1..=self.config.games as u32)
+            .map(|id| self.game(id))
+            .collect()
+    }
+
+    /// The text of `games`, one game per line.
+    pub fn input(&mut self) -> String {
+        self.games()
+            .iter()
+            .map(|game| game.to_string())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use nom::combinator::all_consuming;
+
+    use super::*;
+    use crate::parser;
+
+    fn config() -> GeneratorConfig {
+        GeneratorConfig {
+            games: 50,
+            colors: ["red", "green", "blue", "purple", "teal"]
+                .map(str::to_string)
+                .to_vec(),
+            ..GeneratorConfig::default()
+        }
+    }
+
+    #[test]
+    fn generated_input_parses() {
+        for seed in 0..20 {
+            let games = Generator::new(config(), seed).unwrap().games();
+            let input = Generator::new(config(), seed).unwrap().input();
+            let (_, parsed) = all_consuming(parser::games)(&input).expect("Must parse");
+            assert_eq!(parsed, games);
+            assert_eq!(parser::parse(&input), Ok(games));
+        }
+    }
+
+    #[test]
+    fn generated_input_is_seeded() {
+        let first = Generator::new(config(), 7).unwrap().input();
+        assert_eq!(first, Generator::new(config(), 7).unwrap().input());
+        assert_ne!(first, Generator::new(config(), 8).unwrap().input());
+    }
+
+    #[test]
+    fn generated_input_respects_config() {
+        let config = GeneratorConfig {
+            pulls_per_game: 2..=3,
+            cubes_per_pull: 4..=4,
+            ..config()
+        };
+        let games = Generator::new(config.clone(), 1).unwrap().games();
+        assert_eq!(games.len(), 50);
+        for game in games {
+            assert!((2..=3).contains(&game.pulls().count()));
+            for pull in game.pulls() {
+                let total = pull.cubes().iter().map(|cube| cube.count).sum::<u32>();
+                assert_eq!(total, 4);
+                assert!(pull
+                    .cubes()
+                    .iter()
+                    .all(|cube| config.colors.contains(&cube.color)));
+            }
+        }
+    }
+
+    #[test]
+    fn generated_games_meet_constraints() {
+        let bag = BagState::new(12, 13, 14);
+        for seed in 0..20 {
+            let feasible = GeneratorConfig {
+                constraint: Some(Constraint::Feasible(bag.clone())),
+                ..GeneratorConfig::default()
+            };
+            let games = Generator::new(feasible, seed).unwrap().games();
+            assert!(games
+                .iter()
+                .all(|game| game.possible_with_given_state(&bag)));
+
+            let infeasible = GeneratorConfig {
+                constraint: Some(Constraint::Infeasible(bag.clone())),
+                ..GeneratorConfig::default()
+            };
+            let games = Generator::new(infeasible, seed).unwrap().games();
+            assert!(games
+                .iter()
+                .all(|game| !game.possible_with_given_state(&bag)));
+        }
+    }
+
+    #[test]
+    fn reject_unsatisfiable_configs() {
+        let config = |constraint| GeneratorConfig {
+            constraint: Some(constraint),
+            ..GeneratorConfig::default()
+        };
+        assert_eq!(
+            Generator::new(config(Constraint::Feasible(BagState::default())), 0).unwrap_err(),
+            GeneratorError::Unsatisfiable
+        );
+        let full = BagState::new(u32::MAX, u32::MAX, u32::MAX);
+        assert_eq!(
+            Generator::new(config(Constraint::Infeasible(full)), 0).unwrap_err(),
+            GeneratorError::Unsatisfiable
+        );
+        let no_colors = GeneratorConfig {
+            colors: vec![],
+            ..GeneratorConfig::default()
+        };
+        assert_eq!(
+            Generator::new(no_colors, 0).unwrap_err(),
+            GeneratorError::NoColors
+        );
+    }
+
+    #[test]
+    fn reject_colors_the_parser_would_not_accept() {
+        for color in ["Red", "dark blue", "", "grün"] {
+            let config = GeneratorConfig {
+                colors: vec!["green".to_string(), color.to_string()],
+                ..GeneratorConfig::default()
+            };
+            assert_eq!(
+                Generator::new(config, 0).unwrap_err(),
+                GeneratorError::InvalidColor {
+                    color: color.to_string()
+                }
+            );
+        }
+        let config = GeneratorConfig {
+            colors: vec!["purple".to_string(), "teal".to_string()],
+            ..GeneratorConfig::default()
+        };
+        let input = Generator::new(config, 0).unwrap().input();
+        assert!(parser::parse(&input).is_ok());
+    }
+
+    #[test]
+    fn reject_duplicate_colors() {
+        let config = GeneratorConfig {
+            colors: vec!["red".to_string(), "blue".to_string(), "red".to_string()],
+            constraint: Some(Constraint::Feasible(BagState::new(12, 13, 14))),
+            ..GeneratorConfig::default()
+        };
+        assert_eq!(
+            Generator::new(config, 0).unwrap_err(),
+            GeneratorError::DuplicateColor {
+                color: "red".to_string()
+            }
+        );
+    }
+}
@@ -0,0 +1,345 @@
+//! Probabilistic inference of a game's bag from its pulls.
+//!
+//! Each pull is modelled as a draw without replacement from the whole bag, with the cubes put back
+//! before the next pull. The prior is given over the total number of cubes in the bag; every
+//! split of a given total between the game's colors is considered equally likely.
+
+use std::collections::BTreeMap;
+
+use crate::{BagState, Game};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TotalPrior {
+    weights: BTreeMap<u32, f64>,
+}
+
+impl TotalPrior {
+    /// Every total in `totals` is equally likely.
+    pub fn uniform(totals: impl IntoIterator<Item = u32>) -> Self {
+        Self::from_weights(totals.into_iter().map(|total| (total, 1.0)))
+    }
+
+    /// Relative weights per total. Weights need not sum to one; non-positive weights are dropped.
+    pub fn from_weights(weights: impl IntoIterator<Item = (u32, f64)>) -> Self {
+        let weights = weights
+            .into_iter()
+            .filter(|(_, weight)| *weight > 0.0)
+            .collect();
+        Self { weights }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Inference {
+    /// The bag under which the observed pulls are most likely, ignoring the prior.
+    pub maximum_likelihood: BagState,
+    /// The single most probable bag once the prior is taken into account.
+    pub maximum_a_posteriori: BagState,
+    /// The expected number of cubes of each color.
+    pub posterior_mean: BTreeMap<String, f64>,
+    /// The probability of each total number of cubes in the bag.
+    pub total_posterior: BTreeMap<u32, f64>,
+}
+
+/// Factorials up to this are looked up; larger ones use Stirling's series.
+const TABLED_FACTORIALS: u32 = 1024;
+
+/// Natural logarithms of factorials.
+struct LogFactorials(Vec<f64>);
+
+impl LogFactorials {
+    /// Tables `0!..=n!`, or as much of it as is tabled at all.
+    fn up_to(n: u32) -> Self {
+        let n = n.min(TABLED_FACTORIALS);
+        let mut table = Vec::with_capacity(n as usize + 1);
+        table.push(0.0);
+        for i in 1..=n {
+            table.push(table[i as usize - 1] + (i as f64).ln());
+        }
+        Self(table)
+    }
+
+    fn ln_factorial(&self, n: u32) -> f64 {
+        match self.0.get(n as usize) {
+            Some(&ln) => ln,
+            None => {
+                let n = n as f64;
+                n * n.ln() - n + 0.5 * (2.0 * std::f64::consts::PI * n).ln() + 1.0 / (12.0 * n)
+                    - 1.0 / (360.0 * n.powi(3))
+                    + 1.0 / (1260.0 * n.powi(5))
+            }
+        }
+    }
+
+    fn choose(&self, n: u32, k: u32) -> f64 {
+        self.ln_factorial(n) - self.ln_factorial(k) - self.ln_factorial(n - k)
+    }
+}
+
+/// A running `ln(sum(exp(x)))` over log weights `x`, kept relative to the largest weight seen
+/// so that nothing overflows or underflows.
+struct LogSumExp {
+    max: f64,
+    sum: f64,
+}
+
+impl LogSumExp {
+    fn new() -> Self {
+        Self {
+            max: f64::NEG_INFINITY,
+            sum: 0.0,
+        }
+    }
+
+    /// Adds `exp(x)`. Returns the factor any sums kept relative to the same maximum must be
+    /// multiplied by.
+    fn add(&mut self, x: f64) -> f64 {
+        let rescale = if x > self.max {
+            let rescale = (self.max - x).exp();
+            self.sum *= rescale;
+            self.max = x;
+            rescale
+        } else {
+            1.0
+        };
+        self.sum += self.relative(x);
+        rescale
+    }
+
+    /// `exp(x)` relative to the largest weight seen so far.
+    fn relative(&self, x: f64) -> f64 {
+        (x - self.max).exp()
+    }
+
+    fn ln(&self) -> f64 {
+        self.max + self.sum.ln()
+    }
+}
+
+/// Calls `visit` with every way to share `total` between the colors so that color `i` gets at
+/// least `minimum[i]` cubes.
+fn for_each_composition(total: u32, minimum: &[u32], mut visit: impl FnMut(&[u32])) {
+    fn recurse(
+        remaining: u32,
+        minimum: &[u32],
+        counts: &mut Vec<u32>,
+        visit: &mut impl FnMut(&[u32]),
+    ) {
+        match minimum {
+            [] => {
+                if remaining == 0 {
+                    visit(counts)
+                }
+            }
+            [last] => {
+                if remaining >= *last {
+                    counts.push(remaining);
+                    visit(counts);
+                    counts.pop();
+                }
+            }
+            [first, rest @ ..] => {
+                let reserved = rest.iter().sum::<u32>();
+                for count in *first..=remaining.saturating_sub(reserved) {
+                    counts.push(count);
+                    recurse(remaining - count, rest, counts, visit);
+                    counts.pop();
+                }
+            }
+        }
+    }
+    recurse(total, minimum, &mut Vec::new(), &mut visit);
+}
+
+/// Infers the bag behind `game`. Returns `None` if the game has no pulls or no total in the
+/// prior is large enough to explain them. Totals too close to `u32::MAX` to count the splits of
+/// are skipped.
+///
+/// Every split of every total is visited, `C(total + k - 1, k - 1)` of them for `k` colors, so
+/// the prior should stay small: a few thousand cubes for two colors, a few hundred for three.
+pub fn infer(game: &Game, prior: &TotalPrior) -> Option<Inference> {
+    let minimum_bag = game.bag_state();
+    let colors = minimum_bag.colors().collect::<Vec<_>>();
+    if colors.is_empty() {
+        return None;
+    }
+    let minimum = colors
+        .iter()
+        .map(|color| minimum_bag.count(color))
+        .collect::<Vec<_>>();
+    let pulls = game
+        .pulls()
+        .map(|pull| {
+            colors
+                .iter()
+                .map(|color| pull.bag_state().count(color))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    let max_total = *prior.weights.keys().next_back()?;
+    let k = u32::try_from(colors.len()).ok()?;
+    let log_factorials = LogFactorials::up_to(max_total.saturating_add(k));
+
+    // Only running maxima and sums are kept, never the candidates themselves.
+    let mut maximum_likelihood: Option<(f64, Vec<u32>)> = None;
+    let mut maximum_a_posteriori = Vec::new();
+    let mut evidence = LogSumExp::new();
+    // Sums of count times posterior weight, relative to `evidence`.
+    let mut weighted_counts = vec![0.0; colors.len()];
+    let mut log_total_mass = BTreeMap::new();
+    let minimum_total = minimum.iter().sum::<u32>();
+    for (&total, &weight) in prior.weights.range(minimum_total..) {
+        if total.checked_add(k).is_none() {
+            continue;
+        }
+        // Every split of `total` between `k` colors shares the prior weight of `total`.
+        let log_prior = weight.ln() - log_factorials.choose(total + k - 1, k - 1);
+        let mut total_mass = LogSumExp::new();
+        for_each_composition(total, &minimum, |counts| {
+            let log_likelihood = pulls
+                .iter()
+                .map(|pull| {
+                    let drawn = pull.iter().sum::<u32>();
+                    counts
+                        .iter()
+                        .zip(pull)
+                        .map(|(&have, &took)| log_factorials.choose(have, took))
+                        .sum::<f64>()
+                        - log_factorials.choose(total, drawn)
+                })
+                .sum::<f64>();
+            let log_posterior = log_prior + log_likelihood;
+
+            // Ties go to the first candidate seen.
+            if maximum_likelihood
+                .as_ref()
+                .is_none_or(|(best, _)| log_likelihood > *best)
+            {
+                maximum_likelihood = Some((log_likelihood, counts.to_vec()));
+            }
+            if log_posterior > evidence.max {
+                maximum_a_posteriori = counts.to_vec();
+            }
+            let rescale = evidence.add(log_posterior);
+            let relative = evidence.relative(log_posterior);
+            for (weighted, &count) in weighted_counts.iter_mut().zip(counts) {
+                *weighted = *weighted * rescale + relative * count as f64;
+            }
+            total_mass.add(log_posterior);
+        });
+        if total_mass.sum > 0.0 {
+            log_total_mass.insert(total, total_mass.ln());
+        }
+    }
+
+    let bag = |counts: &[u32]| {
+        colors
+            .iter()
+            .zip(counts)
+            .fold(BagState::default(), |bag, (color, &count)| {
+                bag.with(color, count)
+            })
+    };
+    let maximum_likelihood = bag(&maximum_likelihood?.1);
+    let maximum_a_posteriori = bag(&maximum_a_posteriori);
+    let posterior_mean = colors
+        .iter()
+        .zip(&weighted_counts)
+        .map(|(color, weighted)| (color.to_string(), weighted / evidence.sum))
+        .collect();
+    let log_evidence = evidence.ln();
+    let total_posterior = log_total_mass
+        .into_iter()
+        .map(|(total, log_mass)| (total, (log_mass - log_evidence).exp()))
+        .collect();
+
+    Some(Inference {
+        maximum_likelihood,
+        maximum_a_posteriori,
+        posterior_mean,
+        total_posterior,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser;
+
+    fn assert_close(left: f64, right: f64) {
+        assert!((left - right).abs() < 1e-9, "{left} != {right}");
+    }
+
+    #[test]
+    fn infer_small_bag() {
+        let (_, game) = parser::game("Game 1: 1 red, 1 blue").expect("Must parse");
+        let inference = infer(&game, &TotalPrior::uniform(2..=3)).expect("Must infer");
+
+        let expected = BagState::default().with("red", 1).with("blue", 1);
+        assert_eq!(inference.maximum_likelihood, expected);
+        assert_eq!(inference.maximum_a_posteriori, expected);
+        // (1, 1) has posterior 1/2, (2, 1) and (1, 2) have 1/4 each.
+        assert_close(inference.posterior_mean["red"], 1.25);
+        assert_close(inference.posterior_mean["blue"], 1.25);
+        assert_close(inference.total_posterior[&2], 0.5);
+        assert_close(inference.total_posterior[&3], 0.5);
+    }
+
+    #[test]
+    fn infer_from_repeated_pulls() {
+        let (_, game) = parser::game("Game 1: 3 red; 3 red; 3 red; 1 blue").expect("Must parse");
+        let inference = infer(&game, &TotalPrior::uniform(4..=30)).expect("Must infer");
+
+        // Drawing three reds every time favours bags that are mostly red.
+        assert_eq!(
+            inference.maximum_likelihood,
+            BagState::default().with("red", 27).with("blue", 3)
+        );
+        assert!(inference.posterior_mean["red"] > inference.posterior_mean["blue"]);
+        assert_close(inference.total_posterior.values().sum::<f64>(), 1.0);
+        assert!(inference.total_posterior.keys().all(|&total| total >= 4));
+    }
+
+    #[test]
+    fn infer_needs_a_large_enough_prior() {
+        let (_, game) = parser::game("Game 1: 3 red, 2 blue").expect("Must parse");
+        assert_eq!(infer(&game, &TotalPrior::uniform(1..=4)), None);
+        assert_eq!(infer(&game, &TotalPrior::uniform([])), None);
+    }
+
+    #[test]
+    fn infer_skips_totals_that_overflow() {
+        let (_, game) = parser::game("Game 1: 3 red, 1 blue").expect("Must parse");
+        assert_eq!(infer(&game, &TotalPrior::uniform([u32::MAX])), None);
+
+        let inference = infer(&game, &TotalPrior::uniform([5, u32::MAX])).expect("Must infer");
+        assert_eq!(inference.total_posterior.len(), 1);
+        assert_close(inference.total_posterior[&5], 1.0);
+    }
+
+    #[test]
+    fn infer_with_one_color_and_a_large_total() {
+        // One color has a single split per total, so only the factorials are large here.
+        let (_, game) = parser::game("Game 1: 3 red").expect("Must parse");
+
+        let inference = infer(&game, &TotalPrior::uniform([1_000_000_000])).expect("Must infer");
+        assert_eq!(
+            inference.maximum_likelihood,
+            BagState::default().with("red", 1_000_000_000)
+        );
+        assert_close(inference.total_posterior[&1_000_000_000], 1.0);
+    }
+
+    #[test]
+    fn stirling_matches_the_table() {
+        let table = LogFactorials::up_to(TABLED_FACTORIALS);
+        let stirling = LogFactorials(vec![0.0]);
+        for n in [10, 100, TABLED_FACTORIALS] {
+            let (tabled, approximated) = (table.ln_factorial(n), stirling.ln_factorial(n));
+            assert!(
+                (tabled - approximated).abs() < 1e-9 * tabled,
+                "{n}: {tabled} != {approximated}"
+            );
+        }
+    }
+}
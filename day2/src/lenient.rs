@@ -0,0 +1,206 @@
+//! A forgiving line parser for hand-edited or machine-mangled logs. It accepts any amount of
+//! whitespace around tokens, `Game1:`-style missing spaces, capitalised color names, plurals of
+//! known colors and trailing separators, normalising them and recording a `Warning` for each.
+//! Colors are still ASCII letters only, so every normalised game is accepted by the strict parser.
+
+use crate::{
+    error::{BagError, Expected, ParseError, Warning},
+    Cube, Game, Pull, BLUE, GREEN, RED,
+};
+
+struct Cursor<'a> {
+    line_number: usize,
+    line: &'a str,
+    pos: usize,
+    warnings: Vec<Warning>,
+    /// Colors already read on this line, whose plurals are recognised.
+    colors: Vec<String>,
+}
+
+impl<'a> Cursor<'a> {
+    fn rest(&self) -> &'a str {
+        &self.line[self.pos..]
+    }
+
+    fn column(&self) -> usize {
+        self.line[..self.pos].chars().count() + 1
+    }
+
+    fn at_end(&self) -> bool {
+        self.rest().is_empty()
+    }
+
+    fn error(&self, expected: Expected) -> ParseError {
+        ParseError::Line {
+            line: self.line_number,
+            column: self.column(),
+            text: self.line.to_string(),
+            expected,
+        }
+    }
+
+    fn take_while(&mut self, f: impl Fn(char) -> bool) -> &'a str {
+        let rest = self.rest();
+        let len = rest.find(|c: char| !f(c)).unwrap_or(rest.len());
+        self.pos += len;
+        &rest[..len]
+    }
+
+    /// Skips whitespace, warning unless exactly `canonical` spaces were found.
+    fn space(&mut self, canonical: &str) {
+        let column = self.column();
+        if self.take_while(|c| c == ' ' || c == '\t') != canonical {
+            self.warnings.push(Warning::Whitespace { column });
+        }
+    }
+
+    fn peek(&self, c: char) -> bool {
+        self.rest().starts_with(c)
+    }
+
+    fn char(&mut self, c: char, expected: Expected) -> Result<(), ParseError> {
+        if self.peek(c) {
+            self.pos += c.len_utf8();
+            Ok(())
+        } else {
+            Err(self.error(expected))
+        }
+    }
+
+    fn number(&mut self) -> Result<u32, ParseError> {
+        let start = self.pos;
+        let digits = self.take_while(|c| c.is_ascii_digit());
+        if digits.is_empty() {
+            return Err(self.error(Expected::Number));
+        }
+        digits.parse::<u32>().map_err(|_| {
+            self.pos = start;
+            self.error(Expected::NumberInRange)
+        })
+    }
+
+    fn color(&mut self) -> Result<String, ParseError> {
+        let column = self.column();
+        let word = self.take_while(|c| c.is_ascii_alphabetic());
+        if word.is_empty() {
+            return Err(self.error(Expected::Color));
+        }
+        let mut color = word.to_ascii_lowercase();
+        if color != word {
+            self.warnings.push(Warning::UppercaseColor {
+                column,
+                found: word.to_string(),
+            });
+        }
+        // Only plurals of known colors, so that names like "citrus" survive.
+        if let Some(singular) = color.strip_suffix('s') {
+            if [RED, GREEN, BLUE].contains(&singular) || self.colors.iter().any(|c| c == singular) {
+                color.pop();
+                self.warnings.push(Warning::PluralColor {
+                    column,
+                    found: word.to_string(),
+                });
+            }
+        }
+        if !self.colors.contains(&color) {
+            self.colors.push(color.clone());
+        }
+        Ok(color)
+    }
+
+    fn cube(&mut self) -> Result<Cube, ParseError> {
+        let count = self.number()?;
+        self.space(" ");
+        let color = self.color()?;
+        Ok(Cube::new(&color, count))
+    }
+
+    /// Consumes `separator` and any whitespace after it if the next item is another element;
+    /// a separator with nothing after it is dropped with a warning.
+    fn separator(&mut self, separator: char, canonical_after: &str, ends: &[char]) -> bool {
+        let start = self.pos;
+        let column = self.column();
+        self.take_while(|c| c == ' ' || c == '\t');
+        if !self.peek(separator) {
+            self.pos = start;
+            return false;
+        }
+        if self.pos != start {
+            self.warnings.push(Warning::Whitespace { column });
+        }
+        let column = self.column();
+        self.pos += separator.len_utf8();
+        let after = self.pos;
+        self.take_while(|c| c == ' ' || c == '\t');
+        if self.at_end() || ends.iter().any(|&end| self.peek(end)) {
+            self.warnings.push(Warning::TrailingSeparator { column });
+            self.pos = after;
+            return false;
+        }
+        self.pos = after;
+        self.space(canonical_after);
+        true
+    }
+
+    fn pull(&mut self) -> Result<Pull, ParseError> {
+        let column = self.column();
+        let mut cubes = vec![self.cube()?];
+        while self.separator(',', " ", &[';']) {
+            cubes.push(self.cube()?);
+        }
+        Pull::new(cubes).map_err(|source: BagError| ParseError::Bag {
+            line: self.line_number,
+            column,
+            text: self.line.to_string(),
+            source,
+        })
+    }
+
+    fn game(&mut self) -> Result<Game, ParseError> {
+        self.space("");
+        let column = self.column();
+        let tag = self.take_while(|c| c.is_alphabetic());
+        if !tag.eq_ignore_ascii_case("game") {
+            self.pos -= tag.len();
+            return Err(self.error(Expected::Tag("Game ")));
+        }
+        if tag != "Game" {
+            self.warnings.push(Warning::GameTag {
+                column,
+                found: tag.to_string(),
+            });
+        }
+        self.space(" ");
+        let id = self.number()?;
+        self.space("");
+        self.char(':', Expected::Tag(": "))?;
+        self.space(" ");
+        let mut pulls = vec![self.pull()?];
+        while self.separator(';', " ", &[]) {
+            pulls.push(self.pull()?);
+        }
+        let column = self.column();
+        if !self.take_while(|c| c == ' ' || c == '\t').is_empty() {
+            self.warnings.push(Warning::Whitespace { column });
+        }
+        if !self.at_end() {
+            return Err(self.error(Expected::EndOfPull));
+        }
+        Ok(Game::new(id, pulls).expect("a game always has its first pull"))
+    }
+}
+
+pub(crate) fn parse_line(
+    line_number: usize,
+    line: &str,
+) -> Result<(Game, Vec<Warning>), ParseError> {
+    let mut cursor = Cursor {
+        line_number,
+        line,
+        pos: 0,
+        warnings: Vec::new(),
+        colors: Vec::new(),
+    };
+    let game = cursor.game()?;
+    Ok((game, cursor.warnings))
+}
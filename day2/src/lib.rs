@@ -1,86 +1,411 @@
+pub mod error;
+pub mod generator;
+pub mod inference;
+mod lenient;
+pub mod optimize;
 pub mod parser;
+pub mod query;
+pub mod validate;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+use std::{
+    cmp::Ordering,
+    collections::BTreeMap,
+    convert::Infallible,
+    fmt::Display,
+    iter::Sum,
+    ops::{Add, BitAnd, BitOr, Sub},
+    sync::OnceLock,
+};
+
+use error::{BagError, Warning};
+
+pub const RED: &str = "red";
+pub const GREEN: &str = "green";
+pub const BLUE: &str = "blue";
+
+/// Whether the parser accepts `color`: a non-empty run of ASCII lowercase letters.
+pub(crate) fn is_color(color: &str) -> bool {
+    !color.is_empty() && color.chars().all(|c| c.is_ascii_lowercase())
+}
+
+#[derive(Debug, Clone, Default)]
 pub struct BagState {
-    red: u32,
-    green: u32,
-    blue: u32,
+    counts: BTreeMap<String, u32>,
 }
 
 impl BagState {
     pub fn new(red: u32, green: u32, blue: u32) -> Self {
-        Self { red, green, blue }
+        Self::default()
+            .with(RED, red)
+            .with(GREEN, green)
+            .with(BLUE, blue)
     }
 
-    pub fn add_state(mut self, other: BagState) -> Self {
-        if other.red > self.red {
-            self.red = other.red
-        }
-        if other.green > self.green {
-            self.green = other.green
-        }
-        if other.blue > self.blue {
-            self.blue = other.blue
-        }
+    pub fn with(mut self, color: &str, count: u32) -> Self {
+        self.counts.insert(color.to_string(), count);
         self
     }
 
+    /// The number of cubes of `color` in the bag. Colors the bag has never seen count as zero.
+    pub fn count(&self, color: &str) -> u32 {
+        self.counts.get(color).copied().unwrap_or(0)
+    }
+
+    pub fn colors(&self) -> impl Iterator<Item = &str> {
+        self.counts.keys().map(String::as_str)
+    }
+
+    pub fn counts(&self) -> impl Iterator<Item = (&str, u32)> {
+        self.counts
+            .iter()
+            .map(|(color, count)| (color.as_str(), *count))
+    }
+
+    pub fn add_state(self, other: BagState) -> Self {
+        self | other
+    }
+
+    /// The smallest bag that holds every bag in `bags`.
+    pub fn join_all(bags: impl IntoIterator<Item = BagState>) -> Self {
+        bags.into_iter().fold(Self::default(), |acc, bag| acc | bag)
+    }
+
+    /// Applies `f` to the counts of every color either bag has seen.
+    fn combine<E>(
+        &self,
+        other: &BagState,
+        f: impl Fn(u32, u32) -> Result<u32, E>,
+    ) -> Result<Self, E> {
+        let counts = self
+            .colors()
+            .chain(other.colors())
+            .map(|color| Ok((color.to_string(), f(self.count(color), other.count(color))?)))
+            .collect::<Result<_, E>>()?;
+        Ok(Self { counts })
+    }
+
     pub fn possible_with_given(&self, red: u32, green: u32, blue: u32) -> bool {
-        self.red <= red && self.green <= green && self.blue <= blue
+        self.possible_with_given_state(&BagState::new(red, green, blue))
     }
     pub fn possible_with_given_state(&self, other: &BagState) -> bool {
-        self.possible_with_given(other.red, other.green, other.blue)
+        self <= other
     }
-    pub fn power(&self) -> u32 {
-        self.red * self.green * self.blue
+    /// The product of the given colors, where colors the bag has never seen count as zero.
+    pub fn power_over<'a>(
+        &self,
+        colors: impl IntoIterator<Item = &'a str>,
+    ) -> Result<u64, BagError> {
+        checked_product(colors.into_iter().map(|color| self.count(color)))
     }
 }
-impl Default for BagState {
-    fn default() -> Self {
-        Self::new(0, 0, 0)
+
+fn checked_product(counts: impl Iterator<Item = u32>) -> Result<u64, BagError> {
+    counts.into_iter().try_fold(1u64, |acc, count| {
+        acc.checked_mul(count as u64).ok_or(BagError::Overflow)
+    })
+}
+
+impl PartialEq for BagState {
+    fn eq(&self, other: &Self) -> bool {
+        self.colors()
+            .chain(other.colors())
+            .all(|color| self.count(color) == other.count(color))
     }
 }
+impl Eq for BagState {}
 
-impl From<Vec<Cube>> for BagState {
-    fn from(val: Vec<Cube>) -> Self {
-        let (red, green, blue) = val.into_iter().fold(
-            (0u32, 0u32, 0u32),
-            |(red, green, blue), cube: Cube| match cube {
-                Cube::Red(count) => (red + count, green, blue),
-                Cube::Green(count) => (red, green + count, blue),
-                Cube::Blue(count) => (red, green, blue + count),
-            },
-        );
-        Self::new(red, green, blue)
+/// Bags are ordered by dominance: one bag is less than another if it has no more cubes of any
+/// color. Bags that each have more of some color are incomparable.
+impl PartialOrd for BagState {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        let colors = || self.colors().chain(other.colors());
+        let le = colors().all(|color| self.count(color) <= other.count(color));
+        let ge = colors().all(|color| self.count(color) >= other.count(color));
+        match (le, ge) {
+            (true, true) => Some(Ordering::Equal),
+            (true, false) => Some(Ordering::Less),
+            (false, true) => Some(Ordering::Greater),
+            (false, false) => None,
+        }
+    }
+}
+
+/// Join: the per-color maximum of both bags.
+impl BitOr for &BagState {
+    type Output = BagState;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        let Ok(bag) = self.combine::<Infallible>(rhs, |a, b| Ok(a.max(b)));
+        bag
+    }
+}
+impl BitOr for BagState {
+    type Output = BagState;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        &self | &rhs
+    }
+}
+
+/// Meet: the per-color minimum of both bags.
+impl BitAnd for &BagState {
+    type Output = BagState;
+
+    fn bitand(self, rhs: Self) -> Self::Output {
+        let Ok(bag) = self.combine::<Infallible>(rhs, |a, b| Ok(a.min(b)));
+        bag
+    }
+}
+impl BitAnd for BagState {
+    type Output = BagState;
+
+    fn bitand(self, rhs: Self) -> Self::Output {
+        &self & &rhs
+    }
+}
+
+impl Add for &BagState {
+    type Output = Result<BagState, BagError>;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        self.combine(rhs, |a, b| a.checked_add(b).ok_or(BagError::Overflow))
+    }
+}
+impl Add for BagState {
+    type Output = Result<BagState, BagError>;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        &self + &rhs
+    }
+}
+
+impl Sub for &BagState {
+    type Output = Result<BagState, BagError>;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        self.combine(rhs, |a, b| a.checked_sub(b).ok_or(BagError::Underflow))
+    }
+}
+impl Sub for BagState {
+    type Output = Result<BagState, BagError>;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        &self - &rhs
+    }
+}
+
+/// Adds bags together, stopping at the first color that overflows.
+impl Sum<BagState> for Result<BagState, BagError> {
+    fn sum<I: Iterator<Item = BagState>>(iter: I) -> Self {
+        iter.into_iter()
+            .try_fold(BagState::default(), |acc, bag| acc + bag)
+    }
+}
+impl<'a> Sum<&'a BagState> for Result<BagState, BagError> {
+    fn sum<I: Iterator<Item = &'a BagState>>(iter: I) -> Self {
+        iter.into_iter()
+            .try_fold(BagState::default(), |acc, bag| &acc + bag)
+    }
+}
+
+/// Renders the bag as a single pull, e.g. `6 blue, 2 green, 4 red`. `BagState::with` takes any
+/// color, so the output only parses back if every color is a run of ASCII lowercase letters and
+/// the bag is not empty.
+impl Display for BagState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (idx, (color, count)) in self.counts().enumerate() {
+            if idx > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{} {}", count, color)?;
+        }
+        Ok(())
     }
 }
 
-impl From<Vec<Vec<Cube>>> for BagState {
-    fn from(value: Vec<Vec<Cube>>) -> Self {
+impl TryFrom<Vec<Cube>> for BagState {
+    type Error = BagError;
+
+    fn try_from(val: Vec<Cube>) -> Result<Self, Self::Error> {
+        let counts =
+            val.into_iter()
+                .try_fold(BTreeMap::new(), |mut acc, Cube { color, count }| {
+                    let entry = acc.entry(color).or_insert(0u32);
+                    *entry = entry.checked_add(count).ok_or(BagError::Overflow)?;
+                    Ok(acc)
+                })?;
+        Ok(Self { counts })
+    }
+}
+
+impl From<&Pull> for BagState {
+    fn from(pull: &Pull) -> Self {
+        pull.bag_state.clone()
+    }
+}
+
+impl TryFrom<Vec<Vec<Cube>>> for BagState {
+    type Error = BagError;
+
+    fn try_from(value: Vec<Vec<Cube>>) -> Result<Self, Self::Error> {
         value
             .into_iter()
-            .map(BagState::from)
-            .fold(Self::default(), |acc, bagstate| acc.add_state(bagstate))
+            .map(BagState::try_from)
+            .try_fold(
+                Self::default(),
+                |acc, bagstate| Ok(acc.add_state(bagstate?)),
+            )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cube {
+    pub color: String,
+    pub count: u32,
+}
+
+impl Cube {
+    pub fn new(color: &str, count: u32) -> Self {
+        Self {
+            color: color.to_string(),
+            count,
+        }
     }
 }
 
-pub enum Cube {
-    Red(u32),
-    Green(u32),
-    Blue(u32),
+impl Display for Cube {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.count, self.color)
+    }
 }
 
+/// The cubes revealed by a single handful taken from the bag.
 #[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pull {
+    cubes: Vec<Cube>,
+    bag_state: BagState,
+}
+
+impl Pull {
+    /// Fails if there are no cubes, a color is not one the parser accepts, or the cubes of any
+    /// one color add up to more than a `u32` can hold.
+    pub fn new(cubes: Vec<Cube>) -> Result<Self, BagError> {
+        if cubes.is_empty() {
+            return Err(BagError::EmptyPull);
+        }
+        if let Some(cube) = cubes.iter().find(|cube| !is_color(&cube.color)) {
+            return Err(BagError::InvalidColor {
+                color: cube.color.clone(),
+            });
+        }
+        let bag_state = BagState::try_from(cubes.clone())?;
+        Ok(Self { cubes, bag_state })
+    }
+
+    pub fn cubes(&self) -> &[Cube] {
+        &self.cubes
+    }
+
+    pub fn bag_state(&self) -> &BagState {
+        &self.bag_state
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct Game {
     pub id: u32,
-    pub bag_state: BagState,
+    pulls: Vec<Pull>,
+    bag_state: OnceLock<BagState>,
+    warnings: Vec<Warning>,
 }
 
+impl Game {
+    /// Fails if there are no pulls, so that every game renders as a line the parser accepts.
+    pub fn new(id: u32, pulls: Vec<Pull>) -> Result<Self, BagError> {
+        if pulls.is_empty() {
+            return Err(BagError::EmptyGame);
+        }
+        Ok(Self {
+            id,
+            pulls,
+            bag_state: OnceLock::new(),
+            warnings: Vec::new(),
+        })
+    }
+
+    pub fn with_warnings(mut self, warnings: Vec<Warning>) -> Self {
+        self.warnings.extend(warnings);
+        self
+    }
+
+    /// Irregularities the lenient parser recovered from on this game's line. Always empty for
+    /// games from the strict parser.
+    pub fn warnings(&self) -> &[Warning] {
+        &self.warnings
+    }
+
+    pub fn pulls(&self) -> impl Iterator<Item = &Pull> {
+        self.pulls.iter()
+    }
+
+    /// The smallest bag that could have produced every pull in this game. Computed on first use.
+    pub fn bag_state(&self) -> &BagState {
+        self.bag_state
+            .get_or_init(|| BagState::join_all(self.pulls.iter().map(BagState::from)))
+    }
+
+    /// The first pull (and its index) that could not have come out of `bag`, if any.
+    pub fn impossible_pull(&self, bag: &BagState) -> Option<(usize, &Pull)> {
+        self.pulls
+            .iter()
+            .enumerate()
+            .find(|(_, pull)| !pull.bag_state().possible_with_given_state(bag))
+    }
+
+    pub fn possible_with_given_state(&self, bag: &BagState) -> bool {
+        self.bag_state().possible_with_given_state(bag)
+    }
+}
+
+impl Display for Pull {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (idx, cube) in self.cubes.iter().enumerate() {
+            if idx > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", cube)?;
+        }
+        Ok(())
+    }
+}
+
+/// Renders the game in the same `Game N: ...` form that `parser::game` accepts.
+impl Display for Game {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Game {}: ", self.id)?;
+        for (idx, pull) in self.pulls.iter().enumerate() {
+            if idx > 0 {
+                write!(f, "; ")?;
+            }
+            write!(f, "{}", pull)?;
+        }
+        Ok(())
+    }
+}
+
+impl PartialEq for Game {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id && self.pulls == other.pulls
+    }
+}
+impl Eq for Game {}
+
 #[cfg(test)]
 mod test {
     use super::*;
 
-    const INPUT: &str = "Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green
+    /// The puzzle's example games, shared by the tests of every module.
+    pub(crate) const INPUT: &str = "Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green
 Game 2: 1 blue, 2 green; 3 green, 4 blue, 1 red; 1 green, 1 blue
 Game 3: 8 green, 6 blue, 20 red; 5 blue, 4 red, 13 green; 5 green, 1 red
 Game 4: 1 green, 3 red, 6 blue; 3 green, 6 red; 3 green, 15 blue, 14 red
@@ -88,17 +413,209 @@ Game 5: 6 red, 1 blue, 3 green; 2 blue, 1 red, 2 green";
 
     #[test]
     fn test_is_possible() {
-        let game = Game {
-            id: 1,
-            bag_state: BagState::new(4, 2, 6),
-        };
-        assert!(game.bag_state.possible_with_given(12, 13, 14));
-
-        let game = Game {
-            id: 1,
-            bag_state: BagState::new(17, 2, 6),
-        }; // Too many reds!
-
-        assert!(!game.bag_state.possible_with_given(12, 13, 14));
+        let game = Game::new(
+            1,
+            vec![
+                Pull::new(vec![Cube::new(RED, 4), Cube::new(GREEN, 2)]).unwrap(),
+                Pull::new(vec![Cube::new(BLUE, 6)]).unwrap(),
+            ],
+        )
+        .unwrap();
+        assert_eq!(game.bag_state(), &BagState::new(4, 2, 6));
+        assert!(game.bag_state().possible_with_given(12, 13, 14));
+
+        let game = Game::new(
+            1,
+            vec![
+                Pull::new(vec![Cube::new(RED, 4), Cube::new(GREEN, 2)]).unwrap(),
+                Pull::new(vec![Cube::new(RED, 17), Cube::new(BLUE, 6)]).unwrap(),
+            ],
+        )
+        .unwrap(); // Too many reds!
+
+        assert!(!game.bag_state().possible_with_given(12, 13, 14));
+    }
+
+    #[test]
+    fn test_impossible_pull() {
+        let (_, games) = parser::games(INPUT).expect("Must parse");
+        let given = BagState::new(12, 13, 14);
+        let impossible = games
+            .iter()
+            .filter_map(|game| {
+                let (idx, pull) = game.impossible_pull(&given)?;
+                Some((game.id, idx, pull.bag_state().clone()))
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            impossible,
+            vec![
+                (3, 0, BagState::new(20, 8, 6)),
+                (4, 2, BagState::new(14, 3, 15)),
+            ]
+        );
+        assert_eq!(games[2].pulls().count(), 3);
+    }
+
+    #[test]
+    fn test_arbitrary_colors() {
+        let given = BagState::default()
+            .with("red", 4)
+            .with("purple", 3)
+            .with("teal", 2);
+        let bag = BagState::try_from(vec![Cube::new("purple", 2), Cube::new("purple", 1)])
+            .unwrap()
+            .add_state(BagState::try_from(vec![Cube::new("teal", 2)]).unwrap());
+        assert_eq!(bag, BagState::default().with("purple", 3).with("teal", 2));
+        assert!(bag.possible_with_given_state(&given));
+        assert!(!given.possible_with_given_state(&bag));
+        assert_eq!(bag.power_over(["purple", "teal"]), Ok(6));
+        assert_eq!(bag.power_over([RED, "purple", "teal"]), Ok(0));
+    }
+
+    #[test]
+    fn test_overflow() {
+        let too_many = vec![Cube::new(RED, u32::MAX), Cube::new(RED, 1)];
+        assert_eq!(Pull::new(too_many), Err(BagError::Overflow));
+
+        let bag = BagState::new(u32::MAX, u32::MAX, 1);
+        assert_eq!(
+            bag.power_over([RED, GREEN, BLUE]),
+            Ok(u32::MAX as u64 * u32::MAX as u64)
+        );
+        let bag = bag.with("purple", 2);
+        assert_eq!(
+            bag.power_over([RED, GREEN, BLUE, "purple"]),
+            Err(BagError::Overflow)
+        );
+    }
+
+    #[test]
+    fn test_power_of_absent_and_zero_colors() {
+        let zero_green = BagState::new(4, 0, 6);
+        let no_green = parser::parse("Game 1: 4 red, 6 blue").unwrap()[0]
+            .bag_state()
+            .clone();
+        assert_eq!(zero_green, no_green);
+        assert_eq!(zero_green.power_over([RED, GREEN, BLUE]), Ok(0));
+        assert_eq!(no_green.power_over([RED, GREEN, BLUE]), Ok(0));
+    }
+
+    #[test]
+    fn test_display() {
+        let game = Game::new(
+            12,
+            vec![
+                Pull::new(vec![Cube::new(BLUE, 3), Cube::new("purple", 40)]).unwrap(),
+                Pull::new(vec![Cube::new(RED, 1)]).unwrap(),
+            ],
+        )
+        .unwrap();
+        assert_eq!(game.to_string(), "Game 12: 3 blue, 40 purple; 1 red");
+        assert_eq!(game.bag_state().to_string(), "3 blue, 40 purple, 1 red");
+    }
+
+    #[test]
+    fn test_display_round_trip() {
+        let games = parser::parse(INPUT).expect("Must parse");
+        let rendered = games
+            .iter()
+            .map(|game| game.to_string())
+            .collect::<Vec<_>>()
+            .join("\n");
+        assert_eq!(rendered, INPUT);
+        assert_eq!(parser::parse(&rendered), Ok(games));
+    }
+
+    #[test]
+    fn test_reject_values_that_would_not_round_trip() {
+        assert_eq!(Pull::new(vec![]), Err(BagError::EmptyPull));
+        assert_eq!(Game::new(1, vec![]), Err(BagError::EmptyGame));
+        for color in ["Red", "dark blue", ""] {
+            assert_eq!(
+                Pull::new(vec![Cube::new(color, 1)]),
+                Err(BagError::InvalidColor {
+                    color: color.to_string()
+                })
+            );
+        }
+        // Bags are not checked, so only some of them render as a pull.
+        let bag = BagState::default().with("dark blue", 1);
+        assert!(parser::parse(&format!("Game 1: {}", bag)).is_err());
+    }
+
+    #[test]
+    fn test_bag_state_round_trip() {
+        let bag = BagState::new(4, 0, 6).with("purple", 12);
+        let (_, game) = parser::game(&format!("Game 1: {}", bag)).expect("Must parse");
+        assert_eq!(game.bag_state(), &bag);
+        assert_eq!(game.pulls().count(), 1);
+    }
+
+    #[test]
+    fn test_dominance() {
+        let small = BagState::new(1, 2, 3);
+        let large = BagState::new(1, 5, 3);
+        let other = BagState::new(2, 0, 0);
+        assert!(small < large);
+        assert!(large > small);
+        assert!(small <= small.clone().with("purple", 0));
+        assert_eq!(small.partial_cmp(&other), None);
+        assert!(!small.possible_with_given_state(&other));
+    }
+
+    #[test]
+    fn test_join_and_meet() {
+        let a = BagState::new(1, 5, 0).with("purple", 2);
+        let b = BagState::new(4, 2, 3);
+        assert_eq!(&a | &b, BagState::new(4, 5, 3).with("purple", 2));
+        assert_eq!(&a & &b, BagState::new(1, 2, 0));
+        assert!(&a & &b <= a && a <= &a | &b);
+        assert_eq!(
+            BagState::join_all([a.clone(), b.clone()]),
+            a.clone().add_state(b)
+        );
+    }
+
+    #[test]
+    fn test_checked_arithmetic() {
+        let a = BagState::new(1, 5, 0);
+        let b = BagState::new(4, 2, 3);
+        assert_eq!(&a + &b, Ok(BagState::new(5, 7, 3)));
+        assert_eq!(&b - &BagState::new(4, 1, 0), Ok(BagState::new(0, 1, 3)));
+        assert_eq!(&a - &b, Err(BagError::Underflow));
+        assert_eq!(
+            BagState::new(u32::MAX, 0, 0) + BagState::new(1, 0, 0),
+            Err(BagError::Overflow)
+        );
+        assert_eq!(
+            [a.clone(), b.clone(), a.clone()]
+                .iter()
+                .sum::<Result<_, _>>(),
+            Ok(BagState::new(6, 12, 3))
+        );
+        assert_eq!(
+            vec![a, BagState::new(u32::MAX, 0, 0)]
+                .into_iter()
+                .sum::<Result<BagState, BagError>>(),
+            Err(BagError::Overflow)
+        );
+    }
+
+    #[test]
+    fn test_games_are_shared_across_threads() {
+        let games = parser::parse("Game 1: 3 blue, 4 red, 1 green\nGame 2: 1 red; 2 green, 5 blue")
+            .unwrap();
+        let powers = std::thread::scope(|scope| {
+            let threads = games
+                .iter()
+                .map(|game| scope.spawn(|| game.bag_state().power_over([RED, GREEN, BLUE])))
+                .collect::<Vec<_>>();
+            threads
+                .into_iter()
+                .map(|thread| thread.join().unwrap())
+                .collect::<Vec<_>>()
+        });
+        assert_eq!(powers, vec![Ok(12), Ok(10)]);
     }
 }
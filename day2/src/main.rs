@@ -1,16 +1,26 @@
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use anyhow::Context;
+use day2::error::BagError;
 use day2::parser;
+use day2::parser::{GameReader, Mode};
+use day2::query::Query;
+use day2::validate::validate_ids;
 use day2::BagState;
 use day2::Game;
+use day2::{BLUE, GREEN, RED};
 
 const INPUT: &str = include_str!("input.txt");
 
-fn solve_part1(games: Vec<Game>) -> u32 {
+fn solve_part1(games: impl IntoIterator<Item = Game>) -> u32 {
     let given = BagState::new(12, 13, 14);
-    (1u32..)
-        .zip(games)
-        .filter_map(|(idx, game)| {
-            if game.bag_state.possible_with_given_state(&given) {
-                Some(idx as u32)
+    games
+        .into_iter()
+        .filter_map(|game| {
+            if game.possible_with_given_state(&given) {
+                Some(game.id)
             } else {
                 None
             }
@@ -18,17 +28,86 @@ fn solve_part1(games: Vec<Game>) -> u32 {
         .sum()
 }
 
-fn solve_part2(games: Vec<Game>) -> u64 {
-    games
-        .into_iter()
-        .map(|game| game.bag_state.power() as u64)
-        .sum()
+fn solve_part2(games: impl IntoIterator<Item = Game>) -> Result<u64, BagError> {
+    games.into_iter().try_fold(0u64, |acc, game| {
+        let power = game.bag_state().power_over([RED, GREEN, BLUE])?;
+        acc.checked_add(power).ok_or(BagError::Overflow)
+    })
+}
+
+/// Streams the games in the file at `path` into `solve` one line at a time, stopping at the
+/// first game that fails to read.
+fn solve_file<T>(
+    path: &Path,
+    mode: Mode,
+    solve: impl FnOnce(&mut dyn Iterator<Item = Game>) -> T,
+) -> anyhow::Result<T> {
+    let file = File::open(path).with_context(|| format!("can't open {}", path.display()))?;
+    let mut error = None;
+    let result = {
+        let mut games = GameReader::with_mode(BufReader::new(file), mode)
+            .map_while(|game| game.map_err(|err| error = Some(err)).ok());
+        solve(&mut games)
+    };
+    match error {
+        Some(err) => Err(err.into()),
+        None => Ok(result),
+    }
+}
+
+fn report_warnings(game: &Game) {
+    for warning in game.warnings() {
+        eprintln!("warning: game {}: {}", game.id, warning);
+    }
 }
 
 fn main() -> anyhow::Result<()> {
-    let (_, games) = parser::games(INPUT).expect("Must parse");
+    let mut input_path = None;
+    let mut query = None;
+    let mut mode = Mode::Strict;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--input" => input_path = Some(args.next().context("--input needs a path")?),
+            "--lenient" => mode = Mode::Lenient,
+            _ => query = Some(arg.parse::<Query>()?),
+        }
+    }
+
+    if let Some(path) = input_path {
+        let path = Path::new(&path);
+        if let Some(query) = query {
+            solve_file(path, mode, |games| {
+                for game in games
+                    .inspect(report_warnings)
+                    .filter(|game| query.matches(game.bag_state()))
+                {
+                    println!("{}", game.id);
+                }
+            })?;
+            return Ok(());
+        }
+        let part1 = solve_file(path, mode, |games| {
+            solve_part1(games.inspect(report_warnings))
+        })?;
+        let part2 = solve_file(path, mode, |games| solve_part2(games))??;
+        println!("part1: {}\npart2: {}", part1, part2);
+        return Ok(());
+    }
+
+    let games = parser::parse_with_mode(INPUT, mode)?;
+    games.iter().for_each(report_warnings);
+    for diagnostic in validate_ids(&games) {
+        eprintln!("warning: {}", diagnostic);
+    }
+    if let Some(query) = query {
+        for game in query.filter(&games) {
+            println!("{}", game.id);
+        }
+        return Ok(());
+    }
     let part1 = solve_part1(games.clone());
-    let part2 = solve_part2(games);
+    let part2 = solve_part2(games)?;
     println!("part1: {}\npart2: {}", part1, part2);
     Ok(())
 }
@@ -45,13 +124,31 @@ Game 5: 6 red, 1 blue, 3 green; 2 blue, 1 red, 2 green";
 
     #[test]
     fn test_solve_part1() {
-        let (_, games) = parser::games(INPUT).expect("Must parse");
+        let games = parser::parse(INPUT).expect("Must parse");
         assert_eq!(solve_part1(games), 8);
     }
 
+    #[test]
+    fn test_solve_part1_uses_game_ids() {
+        let games = parser::parse(
+            "Game 10: 3 blue, 4 red
+Game 7: 20 red
+Game 3: 1 green",
+        )
+        .expect("Must parse");
+        assert_eq!(solve_part1(games), 13);
+    }
+
     #[test]
     fn test_solve_part2() {
-        let (_, games) = parser::games(INPUT).expect("Must parse");
-        assert_eq!(solve_part2(games), 2286);
+        let games = parser::parse(INPUT).expect("Must parse");
+        assert_eq!(solve_part2(games), Ok(2286));
+    }
+
+    #[test]
+    fn test_solve_streamed() {
+        let games = || GameReader::new(INPUT.as_bytes()).map(|game| game.expect("Must parse"));
+        assert_eq!(solve_part1(games()), 8);
+        assert_eq!(solve_part2(games()), Ok(2286));
     }
 }
@@ -0,0 +1,229 @@
+//! Searches for bags that make a chosen number or set of games possible. Only per-color counts
+//! that some game actually requires are tried, so the searches are exhaustive but exponential in
+//! the number of colors.
+
+use std::collections::BTreeSet;
+
+use crate::{BagState, Game};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Objective {
+    /// The product of every color seen across the games.
+    Power,
+    /// The number of cubes in the bag.
+    TotalCubes,
+}
+
+struct Candidates {
+    colors: Vec<String>,
+    values: Vec<Vec<u32>>,
+    requirements: Vec<Vec<u32>>,
+}
+
+impl Candidates {
+    fn new(games: &[Game]) -> Self {
+        let colors = games
+            .iter()
+            .flat_map(|game| game.bag_state().colors())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        let requirements = games
+            .iter()
+            .map(|game| {
+                colors
+                    .iter()
+                    .map(|color| game.bag_state().count(color))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let values = (0..colors.len())
+            .map(|idx| {
+                requirements
+                    .iter()
+                    .map(|requirement| requirement[idx])
+                    .chain([0])
+                    .collect::<BTreeSet<_>>()
+                    .into_iter()
+                    .collect()
+            })
+            .collect();
+        Self {
+            colors,
+            values,
+            requirements,
+        }
+    }
+
+    fn feasible_count(&self, counts: &[u32]) -> usize {
+        self.requirements
+            .iter()
+            .filter(|requirement| {
+                requirement
+                    .iter()
+                    .zip(counts)
+                    .all(|(need, have)| need <= have)
+            })
+            .count()
+    }
+
+    fn cost(&self, counts: &[u32], objective: Objective) -> u64 {
+        match objective {
+            Objective::Power => counts
+                .iter()
+                .try_fold(1u64, |acc, &count| acc.checked_mul(count as u64))
+                .unwrap_or(u64::MAX),
+            Objective::TotalCubes => counts.iter().map(|&count| count as u64).sum(),
+        }
+    }
+
+    fn bag(&self, counts: &[u32]) -> BagState {
+        self.colors
+            .iter()
+            .zip(counts)
+            .fold(BagState::default(), |bag, (color, &count)| {
+                bag.with(color, count)
+            })
+    }
+
+    /// Calls `visit` with every combination of candidate counts whose running total stays within
+    /// `budget`.
+    fn for_each(&self, budget: u64, mut visit: impl FnMut(&[u32])) {
+        fn recurse(
+            values: &[Vec<u32>],
+            counts: &mut Vec<u32>,
+            total: u64,
+            budget: u64,
+            visit: &mut impl FnMut(&[u32]),
+        ) {
+            let Some((choices, rest)) = values.split_first() else {
+                visit(counts);
+                return;
+            };
+            for &value in choices {
+                let total = total + value as u64;
+                if total > budget {
+                    break;
+                }
+                counts.push(value);
+                recurse(rest, counts, total, budget, visit);
+                counts.pop();
+            }
+        }
+        recurse(&self.values, &mut Vec::new(), 0, budget, &mut visit);
+    }
+}
+
+/// The cheapest bag that makes at least `n` of `games` possible, or `None` if there are fewer
+/// than `n` games.
+pub fn smallest_bag_for_count(games: &[Game], n: usize, objective: Objective) -> Option<BagState> {
+    if n > games.len() {
+        return None;
+    }
+    let candidates = Candidates::new(games);
+    let mut best: Option<(u64, Vec<u32>)> = None;
+    candidates.for_each(u64::MAX, |counts| {
+        let cost = candidates.cost(counts, objective);
+        if best
+            .as_ref()
+            .is_some_and(|(best_cost, _)| *best_cost <= cost)
+        {
+            return;
+        }
+        if candidates.feasible_count(counts) >= n {
+            best = Some((cost, counts.to_vec()));
+        }
+    });
+    best.map(|(_, counts)| candidates.bag(&counts))
+}
+
+/// The smallest bag that makes the games with the given IDs possible and every other game
+/// impossible, if there is one.
+pub fn bag_for_exactly(games: &[Game], ids: &[u32]) -> Option<BagState> {
+    let (inside, outside): (Vec<_>, Vec<_>) = games.iter().partition(|game| ids.contains(&game.id));
+    let bag = BagState::join_all(inside.iter().map(|game| game.bag_state().clone()));
+    if outside
+        .iter()
+        .any(|game| game.possible_with_given_state(&bag))
+    {
+        None
+    } else {
+        Some(bag)
+    }
+}
+
+/// The bag with at most `budget` cubes that makes the most games possible, along with how many
+/// games that is. Ties go to the bag with the fewest cubes.
+pub fn best_bag_under_budget(games: &[Game], budget: u64) -> (BagState, usize) {
+    let candidates = Candidates::new(games);
+    let mut best: Option<(usize, u64, Vec<u32>)> = None;
+    candidates.for_each(budget, |counts| {
+        let feasible = candidates.feasible_count(counts);
+        let cost = candidates.cost(counts, Objective::TotalCubes);
+        let better = match &best {
+            None => true,
+            Some((best_feasible, best_cost, _)) => {
+                feasible > *best_feasible || (feasible == *best_feasible && cost < *best_cost)
+            }
+        };
+        if better {
+            best = Some((feasible, cost, counts.to_vec()));
+        }
+    });
+    let (feasible, _, counts) = best.expect("the empty bag is always within budget");
+    (candidates.bag(&counts), feasible)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{parser, test::INPUT};
+
+    #[test]
+    fn test_smallest_bag_for_count() {
+        let games = parser::parse(INPUT).expect("Must parse");
+        assert_eq!(
+            smallest_bag_for_count(&games, 1, Objective::Power),
+            Some(BagState::new(1, 3, 4))
+        );
+        assert_eq!(
+            smallest_bag_for_count(&games, 3, Objective::TotalCubes),
+            Some(BagState::new(6, 3, 6))
+        );
+        assert_eq!(
+            smallest_bag_for_count(&games, 5, Objective::TotalCubes),
+            Some(BagState::new(20, 13, 15))
+        );
+        assert_eq!(
+            smallest_bag_for_count(&games, 0, Objective::TotalCubes),
+            Some(BagState::new(0, 0, 0))
+        );
+        assert_eq!(smallest_bag_for_count(&games, 6, Objective::Power), None);
+    }
+
+    #[test]
+    fn test_bag_for_exactly() {
+        let games = parser::parse(INPUT).expect("Must parse");
+        assert_eq!(
+            bag_for_exactly(&games, &[1, 2, 5]),
+            Some(BagState::new(6, 3, 6))
+        );
+        // Any bag holding games 1 and 5 also holds game 2.
+        assert_eq!(bag_for_exactly(&games, &[1, 5]), None);
+    }
+
+    #[test]
+    fn test_best_bag_under_budget() {
+        let games = parser::parse(INPUT).expect("Must parse");
+        assert_eq!(
+            best_bag_under_budget(&games, 15),
+            (BagState::new(6, 3, 6), 3)
+        );
+        assert_eq!(
+            best_bag_under_budget(&games, 100),
+            (BagState::new(20, 13, 15), 5)
+        );
+        assert_eq!(best_bag_under_budget(&games, 5), (BagState::default(), 0));
+    }
+}
@@ -1,105 +1,566 @@
+use std::{io::BufRead, num::ParseIntError};
+
 use nom::{
-    branch::alt,
-    bytes::complete::tag,
+    bytes::complete::{tag, take_while1},
     character::complete::{char, digit1, line_ending},
-    combinator::map_res,
-    multi::separated_list1,
-    sequence::{preceded, separated_pair},
+    combinator::{all_consuming, cut, map, map_res},
+    error::{ErrorKind, FromExternalError, ParseError as NomParseError},
+    multi::{many0, separated_list1},
+    sequence::{pair, preceded, separated_pair},
     IResult,
 };
 
-use crate::{Cube, Game};
+use crate::{
+    error::{BagError, Expected, ParseError, ReadError, Warning},
+    lenient, Cube, Game, Pull,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameError<'a> {
+    pub input: &'a str,
+    pub expected: Expected,
+    pub bag_error: Option<BagError>,
+}
+
+impl<'a> NomParseError<&'a str> for GameError<'a> {
+    fn from_error_kind(input: &'a str, kind: ErrorKind) -> Self {
+        let expected = match kind {
+            ErrorKind::Eof => Expected::EndOfPull,
+            kind => Expected::Other(kind),
+        };
+        Self {
+            input,
+            expected,
+            bag_error: None,
+        }
+    }
+
+    fn append(_: &'a str, _: ErrorKind, other: Self) -> Self {
+        other
+    }
+}
+
+impl<'a> FromExternalError<&'a str, ParseIntError> for GameError<'a> {
+    fn from_external_error(input: &'a str, _: ErrorKind, _: ParseIntError) -> Self {
+        Self {
+            input,
+            expected: Expected::NumberInRange,
+            bag_error: None,
+        }
+    }
+}
+
+impl<'a> FromExternalError<&'a str, BagError> for GameError<'a> {
+    fn from_external_error(input: &'a str, kind: ErrorKind, err: BagError) -> Self {
+        Self {
+            bag_error: Some(err),
+            ..Self::from_error_kind(input, kind)
+        }
+    }
+}
+
+type GameResult<'a, O> = IResult<&'a str, O, GameError<'a>>;
+
+/// Labels errors raised directly by `parser` with `expected`. Errors that already carry a
+/// label from a more specific parser are passed through untouched.
+fn expecting<'a, O>(
+    expected: Expected,
+    mut parser: impl FnMut(&'a str) -> GameResult<'a, O>,
+) -> impl FnMut(&'a str) -> GameResult<'a, O> {
+    move |input| {
+        parser(input).map_err(|err| {
+            err.map(|mut err| {
+                if matches!(err.expected, Expected::Other(_)) {
+                    err.expected = expected;
+                }
+                err
+            })
+        })
+    }
+}
 
-fn cube(input: &str) -> IResult<&str, Cube> {
-    let (input, result) = separated_pair(
+fn sep<'a>(separator: &'static str) -> impl FnMut(&'a str) -> GameResult<'a, &'a str> {
+    expecting(Expected::Tag(separator), tag(separator))
+}
+
+/// Like `separated_list1`, but once a separator has been consumed the next element must parse,
+/// so errors point at the broken element rather than at the separator before it.
+fn committed_list1<'a, O>(
+    separator: &'static str,
+    element: impl FnMut(&'a str) -> GameResult<'a, O> + Copy,
+) -> impl FnMut(&'a str) -> GameResult<'a, Vec<O>> {
+    map(
+        pair(element, many0(preceded(tag(separator), cut(element)))),
+        |(first, mut rest)| {
+            rest.insert(0, first);
+            rest
+        },
+    )
+}
+
+fn number(input: &str) -> GameResult<'_, u32> {
+    expecting(
+        Expected::Number,
         map_res(digit1, |m: &str| m.parse::<u32>()),
-        char(' '),
-        alt((tag("red"), tag("green"), tag("blue"))),
-    )(input)?;
-    let result = match result {
-        (count, "red") => Cube::Red(count),
-        (count, "green") => Cube::Green(count),
-        (count, "blue") => Cube::Blue(count),
-        (_, &_) => unreachable!("color can only be red, green, blue"),
-    };
-    Ok((input, result))
+    )(input)
+}
+
+fn color(input: &str) -> GameResult<'_, &str> {
+    expecting(
+        Expected::Color,
+        take_while1(|c: char| c.is_ascii_lowercase()),
+    )(input)
 }
 
-fn pull(input: &str) -> IResult<&str, Vec<Cube>> {
-    separated_list1(tag(", "), cube)(input)
+fn cube(input: &str) -> GameResult<'_, Cube> {
+    map(
+        separated_pair(number, expecting(Expected::Tag(" "), char(' ')), color),
+        |(count, color)| Cube::new(color, count),
+    )(input)
 }
 
-fn pulls(input: &str) -> IResult<&str, Vec<Vec<Cube>>> {
-    separated_list1(tag("; "), pull)(input)
+fn pull(input: &str) -> GameResult<'_, Pull> {
+    map_res(committed_list1(", ", cube), Pull::new)(input)
 }
 
-fn game_id(input: &str) -> IResult<&str, u32> {
-    preceded(tag("Game "), map_res(digit1, |m: &str| m.parse::<u32>()))(input)
+fn pulls(input: &str) -> GameResult<'_, Vec<Pull>> {
+    committed_list1("; ", pull)(input)
 }
 
-pub fn game(input: &str) -> IResult<&str, Game> {
-    let (input, (id, pulls)) = separated_pair(game_id, tag(": "), pulls)(input)?;
-    let game = Game {
-        id,
-        bag_state: pulls.into(),
-    };
+fn game_id(input: &str) -> GameResult<'_, u32> {
+    preceded(sep("Game "), number)(input)
+}
+
+pub fn game(input: &str) -> GameResult<'_, Game> {
+    let (input, (id, pulls)) = separated_pair(game_id, sep(": "), pulls)(input)?;
+    let game = Game::new(id, pulls).expect("a list of pulls is never empty");
     Ok((input, game))
 }
 
-pub fn games(input: &str) -> IResult<&str, Vec<Game>> {
+pub fn games(input: &str) -> GameResult<'_, Vec<Game>> {
     separated_list1(line_ending, game)(input)
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Mode {
+    /// Only the exact `Game N: a red, b blue; ...` grammar.
+    #[default]
+    Strict,
+    /// Also accepts irregular whitespace, plural or capitalised colors, trailing separators and
+    /// blank lines, recording each as a warning on the game it was found in.
+    Lenient,
+}
+
+/// Parses one game per line, reporting the position of the first line that does not parse.
+/// Trailing newlines at the end of the input are ignored.
+pub fn parse(input: &str) -> Result<Vec<Game>, ParseError> {
+    parse_with_mode(input, Mode::Strict)
+}
+
+pub fn parse_with_mode(input: &str, mode: Mode) -> Result<Vec<Game>, ParseError> {
+    let mut games = Vec::new();
+    let mut blank_lines = None;
+    for (idx, line) in input.trim_end_matches(['\r', '\n']).lines().enumerate() {
+        let line_number = idx + 1;
+        if mode == Mode::Lenient && line.trim().is_empty() {
+            let (_, count) = blank_lines.get_or_insert((line_number, 0));
+            *count += 1;
+            continue;
+        }
+        games.push(parse_line_with_mode(
+            mode,
+            line_number,
+            line,
+            blank_line_warning(blank_lines.take()),
+        )?);
+    }
+    Ok(games)
+}
+
+/// Reads one game per line from `reader` without holding more than one line in memory. Blank
+/// lines are handled the same way as in `parse_with_mode`. Parse errors are yielded in place of
+/// the offending game and reading carries on; an I/O error ends the iteration.
+pub struct GameReader<R> {
+    reader: R,
+    mode: Mode,
+    buffer: String,
+    line_number: usize,
+    /// The first blank line since the last game, and how many there have been.
+    blank_lines: Option<(usize, usize)>,
+    /// Set when `buffer` holds a line that has been read but not parsed yet.
+    pending: bool,
+    done: bool,
+}
+
+impl<R: BufRead> GameReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self::with_mode(reader, Mode::Strict)
+    }
+
+    pub fn with_mode(reader: R, mode: Mode) -> Self {
+        Self {
+            reader,
+            mode,
+            buffer: String::new(),
+            line_number: 0,
+            blank_lines: None,
+            pending: false,
+            done: false,
+        }
+    }
+
+    fn parse_buffer(&self, warnings: Vec<Warning>) -> Result<Game, ReadError> {
+        let line = self.buffer.trim_end_matches(['\r', '\n']);
+        Ok(parse_line_with_mode(
+            self.mode,
+            self.line_number,
+            line,
+            warnings,
+        )?)
+    }
+}
+
+impl<R: BufRead> Iterator for GameReader<R> {
+    type Item = Result<Game, ReadError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.pending {
+            self.pending = false;
+            return Some(self.parse_buffer(Vec::new()));
+        }
+        while !self.done {
+            self.buffer.clear();
+            match self.reader.read_line(&mut self.buffer) {
+                Ok(0) => self.done = true,
+                Ok(_) => {
+                    self.line_number += 1;
+                    let blank = match self.mode {
+                        Mode::Strict => self.buffer.trim_end_matches(['\r', '\n']).is_empty(),
+                        Mode::Lenient => self.buffer.trim().is_empty(),
+                    };
+                    if blank {
+                        let (_, count) = self.blank_lines.get_or_insert((self.line_number, 0));
+                        *count += 1;
+                        continue;
+                    }
+                    return Some(match (self.mode, self.blank_lines.take()) {
+                        (Mode::Strict, Some((line, _))) => {
+                            self.pending = true;
+                            parse_line(line, "").map_err(ReadError::from)
+                        }
+                        (_, blank_lines) => self.parse_buffer(blank_line_warning(blank_lines)),
+                    });
+                }
+                Err(err) => {
+                    self.done = true;
+                    return Some(Err(err.into()));
+                }
+            }
+        }
+        None
+    }
+}
+
+fn blank_line_warning(blank_lines: Option<(usize, usize)>) -> Vec<Warning> {
+    blank_lines
+        .map(|(line, count)| Warning::BlankLines { line, count })
+        .into_iter()
+        .collect()
+}
+
+/// Parses `line`, attaching `warnings` ahead of any the line itself produces.
+fn parse_line_with_mode(
+    mode: Mode,
+    line_number: usize,
+    line: &str,
+    warnings: Vec<Warning>,
+) -> Result<Game, ParseError> {
+    match mode {
+        Mode::Strict => Ok(parse_line(line_number, line)?.with_warnings(warnings)),
+        Mode::Lenient => {
+            let (game, line_warnings) = lenient::parse_line(line_number, line)?;
+            Ok(game.with_warnings(warnings).with_warnings(line_warnings))
+        }
+    }
+}
+
+fn parse_line(line_number: usize, line: &str) -> Result<Game, ParseError> {
+    match all_consuming(game)(line) {
+        Ok((_, game)) => Ok(game),
+        Err(nom::Err::Error(err)) | Err(nom::Err::Failure(err)) => {
+            let offset = line.len() - err.input.len();
+            let column = line[..offset].chars().count() + 1;
+            let text = line.to_string();
+            Err(match err.bag_error {
+                Some(source) => ParseError::Bag {
+                    line: line_number,
+                    column,
+                    text,
+                    source,
+                },
+                None => ParseError::Line {
+                    line: line_number,
+                    column,
+                    text,
+                    expected: err.expected,
+                },
+            })
+        }
+        Err(nom::Err::Incomplete(_)) => unreachable!("complete parsers never ask for more input"),
+    }
+}
 #[cfg(test)]
 mod test {
-    use nom::combinator::all_consuming;
 
     use super::*;
-    use crate::BagState;
-
-    const INPUT: &str = "Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green
-Game 2: 1 blue, 2 green; 3 green, 4 blue, 1 red; 1 green, 1 blue
-Game 3: 8 green, 6 blue, 20 red; 5 blue, 4 red, 13 green; 5 green, 1 red
-Game 4: 1 green, 3 red, 6 blue; 3 green, 6 red; 3 green, 15 blue, 14 red
-Game 5: 6 red, 1 blue, 3 green; 2 blue, 1 red, 2 green";
+    use crate::{test::INPUT, BagState};
 
     #[test]
     fn parse_game() {
         let given = "Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green";
-        let expected = Game {
-            id: 1,
-            bag_state: BagState::new(4, 2, 6),
-        };
+        let expected = Game::new(
+            1,
+            vec![
+                Pull::new(vec![Cube::new("blue", 3), Cube::new("red", 4)]).unwrap(),
+                Pull::new(vec![
+                    Cube::new("red", 1),
+                    Cube::new("green", 2),
+                    Cube::new("blue", 6),
+                ])
+                .unwrap(),
+                Pull::new(vec![Cube::new("green", 2)]).unwrap(),
+            ],
+        )
+        .unwrap();
         let (_, result) = all_consuming(game)(given).expect("Must parse");
         assert_eq!(result, expected);
+        assert_eq!(result.bag_state(), &BagState::new(4, 2, 6));
     }
 
     #[test]
     fn parse_games() {
         let given = INPUT;
         let expected = vec![
-            Game {
-                id: 1,
-                bag_state: BagState::new(4, 2, 6),
-            },
-            Game {
-                id: 2,
-                bag_state: BagState::new(1, 3, 4),
-            },
-            Game {
-                id: 3,
-                bag_state: BagState::new(20, 13, 6),
-            },
-            Game {
-                id: 4,
-                bag_state: BagState::new(14, 3, 15),
-            },
-            Game {
-                id: 5,
-                bag_state: BagState::new(6, 3, 2),
-            },
+            (1, BagState::new(4, 2, 6)),
+            (2, BagState::new(1, 3, 4)),
+            (3, BagState::new(20, 13, 6)),
+            (4, BagState::new(14, 3, 15)),
+            (5, BagState::new(6, 3, 2)),
         ];
         let (_, result) = all_consuming(games)(given).expect("Must parse");
-        assert_eq!(result, expected);
+        assert_eq!(
+            result
+                .iter()
+                .map(|game| (game.id, game.bag_state().clone()))
+                .collect::<Vec<_>>(),
+            expected
+        );
+    }
+
+    #[test]
+    fn parse_game_with_other_colors() {
+        let given = "Game 7: 3 purple, 4 red; 1 teal, 2 purple";
+        let expected = BagState::default()
+            .with("purple", 3)
+            .with("red", 4)
+            .with("teal", 1);
+        let (_, result) = all_consuming(game)(given).expect("Must parse");
+        assert_eq!(result.id, 7);
+        assert_eq!(result.bag_state(), &expected);
+    }
+
+    #[test]
+    fn parse_reports_position() {
+        let given = "Game 1: 3 blue, 4 red
+Game 2: 1 blue; 3 Purple, 4 blue
+Game 3: 1 blue";
+        let expected = ParseError::Line {
+            line: 2,
+            column: 19,
+            text: "Game 2: 1 blue; 3 Purple, 4 blue".to_string(),
+            expected: Expected::Color,
+        };
+        assert_eq!(parse(given), Err(expected));
+    }
+
+    #[test]
+    fn parse_reports_what_was_expected() {
+        let expected_at = |line: &str| match parse(line) {
+            Err(ParseError::Line {
+                column, expected, ..
+            }) => (column, expected),
+            Err(err) => panic!("unexpected error {err:?}"),
+            Ok(_) => panic!("{line:?} should not parse"),
+        };
+        assert_eq!(expected_at("Gme 1: 3 blue"), (1, Expected::Tag("Game ")));
+        assert_eq!(expected_at("Game x: 3 blue"), (6, Expected::Number));
+        assert_eq!(expected_at("Game 1 3 blue"), (7, Expected::Tag(": ")));
+        assert_eq!(expected_at("Game 1: 3blue"), (10, Expected::Tag(" ")));
+        assert_eq!(expected_at("Game 1: 3 blue, "), (17, Expected::Number));
+        assert_eq!(
+            expected_at("Game 1: 3 blue;2 red"),
+            (15, Expected::EndOfPull)
+        );
+        assert_eq!(
+            expected_at("Game 1: 99999999999 blue"),
+            (9, Expected::NumberInRange)
+        );
+    }
+
+    #[test]
+    fn parse_reports_overflowing_pulls() {
+        let given = "Game 1: 3 blue; 4294967295 red, 1 red";
+        let expected = ParseError::Bag {
+            line: 1,
+            column: 17,
+            text: given.to_string(),
+            source: BagError::Overflow,
+        };
+        assert_eq!(parse(given), Err(expected));
+    }
+
+    #[test]
+    fn parse_accepts_trailing_newline() {
+        let result = parse(&format!("{INPUT}\n\n")).expect("Must parse");
+        assert_eq!(result.len(), 5);
+    }
+
+    #[test]
+    fn parse_rejects_blank_lines_between_games() {
+        let given = "Game 1: 3 blue\n\nGame 2: 3 blue";
+        assert!(matches!(
+            parse(given),
+            Err(ParseError::Line { line: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn read_games() {
+        let given = format!("{INPUT}\r\n\n");
+        let result = GameReader::new(given.as_bytes())
+            .collect::<Result<Vec<_>, _>>()
+            .expect("Must parse");
+        assert_eq!(result, parse(INPUT).expect("Must parse"));
+    }
+
+    #[test]
+    fn read_games_reports_errors_and_continues() {
+        let given = "Game 1: 3 blue\nGame 2 3 blue\n\nGame 3: 3 blue\n";
+        let result = GameReader::new(given.as_bytes())
+            .map(|game| match game {
+                Ok(game) => Ok(game.id),
+                Err(ReadError::Parse(ParseError::Line { line, .. })) => Err(line),
+                Err(err) => panic!("unexpected error {err:?}"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(result, vec![Ok(1), Err(2), Err(3), Ok(3)]);
+    }
+
+    #[test]
+    fn parse_lenient() {
+        let given = "  game 1 :3 Blues,4 red ;1 red, 2 green,\t6 blue;
+\t
+Game 2: 1 blue, 2 greens; 3 green;";
+        let games = parse_with_mode(given, Mode::Lenient).expect("Must parse");
+        let canonical = "Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue
+Game 2: 1 blue, 2 green; 3 green";
+        assert_eq!(games, parse(canonical).expect("Must parse"));
+        assert_eq!(
+            games[0].warnings(),
+            &[
+                Warning::Whitespace { column: 1 },
+                Warning::GameTag {
+                    column: 3,
+                    found: "game".to_string()
+                },
+                Warning::Whitespace { column: 9 },
+                Warning::Whitespace { column: 11 },
+                Warning::UppercaseColor {
+                    column: 13,
+                    found: "Blues".to_string()
+                },
+                Warning::PluralColor {
+                    column: 13,
+                    found: "Blues".to_string()
+                },
+                Warning::Whitespace { column: 19 },
+                Warning::Whitespace { column: 24 },
+                Warning::Whitespace { column: 26 },
+                Warning::Whitespace { column: 41 },
+                Warning::TrailingSeparator { column: 48 },
+            ]
+        );
+        assert_eq!(
+            games[1].warnings(),
+            &[
+                Warning::BlankLines { line: 2, count: 1 },
+                Warning::PluralColor {
+                    column: 19,
+                    found: "greens".to_string()
+                },
+                Warning::TrailingSeparator { column: 34 },
+            ]
+        );
+        assert!(parse(given).is_err());
+    }
+
+    #[test]
+    fn parse_lenient_colors_round_trip() {
+        let games = parse_with_mode("Game 1: 3 citrus, 1 pink; 2 Pinks, 4 Reds", Mode::Lenient)
+            .expect("Must parse");
+        let canonical = games[0].to_string();
+        assert_eq!(canonical, "Game 1: 3 citrus, 1 pink; 2 pink, 4 red");
+        assert_eq!(parse(&canonical).expect("Must parse"), games);
+        // "pinks" comes before any "pink", so it is taken as its own color.
+        let games = parse_with_mode("Game 1: 2 pinks; 1 pink", Mode::Lenient).expect("Must parse");
+        assert_eq!(games[0].to_string(), "Game 1: 2 pinks; 1 pink");
+        assert!(parse_with_mode("Game 1: 3 Grün", Mode::Lenient).is_err());
+    }
+
+    #[test]
+    fn parse_lenient_still_rejects_garbage() {
+        let expected_at = |line: &str| match parse_with_mode(line, Mode::Lenient) {
+            Err(ParseError::Line {
+                column, expected, ..
+            }) => (column, expected),
+            Err(err) => panic!("unexpected error {err:?}"),
+            Ok(_) => panic!("{line:?} should not parse"),
+        };
+        assert_eq!(expected_at("Gme 1: 3 blue"), (1, Expected::Tag("Game ")));
+        assert_eq!(expected_at("Game 1 3 blue"), (8, Expected::Tag(": ")));
+        assert_eq!(
+            expected_at("Game 1: 3 blue, , 4 red"),
+            (17, Expected::Number)
+        );
+        assert_eq!(
+            expected_at("Game 1: 3 blue 4 red"),
+            (16, Expected::EndOfPull)
+        );
+        assert_eq!(expected_at("Game 1: 3 4"), (11, Expected::Color));
+    }
+
+    #[test]
+    fn read_games_lenient() {
+        let given = "Game 1: 3 blue\n\n\nGame 2: 3 Blue\n\n";
+        let games = GameReader::with_mode(given.as_bytes(), Mode::Lenient)
+            .collect::<Result<Vec<_>, _>>()
+            .expect("Must parse");
+        assert_eq!(games.len(), 2);
+        assert_eq!(
+            games[1].warnings(),
+            &[
+                Warning::BlankLines { line: 2, count: 2 },
+                Warning::UppercaseColor {
+                    column: 11,
+                    found: "Blue".to_string()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn reject_non_color_words() {
+        assert!(all_consuming(game)("Game 1: 3 Purple").is_err());
+        assert!(all_consuming(game)("Game 1: 3 red2").is_err());
     }
 }
@@ -0,0 +1,266 @@
+use std::str::FromStr;
+
+use nom::{
+    branch::alt,
+    bytes::complete::{tag, take_while1},
+    character::complete::{char, digit1, multispace0},
+    combinator::{all_consuming, map, map_res, not, peek, verify},
+    multi::{many0, separated_list1},
+    sequence::{delimited, pair, preceded, terminated, tuple},
+    IResult,
+};
+
+use crate::{error::QueryError, BagState, Game};
+
+const KEYWORDS: [&str; 3] = ["and", "or", "not"];
+
+/// A boolean question about a bag, e.g. `red <= 12 and green + blue <= 25`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Query {
+    Compare(Sum, Comparison, Sum),
+    And(Box<Query>, Box<Query>),
+    Or(Box<Query>, Box<Query>),
+    Not(Box<Query>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Less,
+    LessOrEqual,
+    Equal,
+    NotEqual,
+    GreaterOrEqual,
+    Greater,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Term {
+    Count(u64),
+    Color(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sum(pub Vec<Term>);
+
+impl Sum {
+    fn evaluate(&self, bag: &BagState) -> u64 {
+        self.0.iter().fold(0u64, |acc, term| {
+            let value = match term {
+                Term::Count(count) => *count,
+                Term::Color(color) => bag.count(color) as u64,
+            };
+            acc.saturating_add(value)
+        })
+    }
+}
+
+impl Comparison {
+    fn holds(&self, left: u64, right: u64) -> bool {
+        match self {
+            Self::Less => left < right,
+            Self::LessOrEqual => left <= right,
+            Self::Equal => left == right,
+            Self::NotEqual => left != right,
+            Self::GreaterOrEqual => left >= right,
+            Self::Greater => left > right,
+        }
+    }
+}
+
+impl Query {
+    pub fn matches(&self, bag: &BagState) -> bool {
+        match self {
+            Self::Compare(left, comparison, right) => {
+                comparison.holds(left.evaluate(bag), right.evaluate(bag))
+            }
+            Self::And(left, right) => left.matches(bag) && right.matches(bag),
+            Self::Or(left, right) => left.matches(bag) || right.matches(bag),
+            Self::Not(query) => !query.matches(bag),
+        }
+    }
+
+    /// The games whose minimum bag satisfies this query.
+    pub fn filter<'a>(
+        &'a self,
+        games: impl IntoIterator<Item = &'a Game> + 'a,
+    ) -> impl Iterator<Item = &'a Game> + 'a {
+        games
+            .into_iter()
+            .filter(|game| self.matches(game.bag_state()))
+    }
+}
+
+impl FromStr for Query {
+    type Err = QueryError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match all_consuming(delimited(multispace0, query, multispace0))(s) {
+            Ok((_, query)) => Ok(query),
+            Err(nom::Err::Error(err)) | Err(nom::Err::Failure(err)) => Err(QueryError {
+                column: s[..s.len() - err.input.len()].chars().count() + 1,
+                query: s.to_string(),
+            }),
+            Err(nom::Err::Incomplete(_)) => {
+                unreachable!("complete parsers never ask for more input")
+            }
+        }
+    }
+}
+
+fn ws<'a, O>(
+    parser: impl FnMut(&'a str) -> IResult<&'a str, O>,
+) -> impl FnMut(&'a str) -> IResult<&'a str, O> {
+    delimited(multispace0, parser, multispace0)
+}
+
+fn word(input: &str) -> IResult<&str, &str> {
+    take_while1(|c: char| c.is_ascii_lowercase())(input)
+}
+
+fn keyword<'a>(name: &'static str) -> impl FnMut(&'a str) -> IResult<&'a str, &'a str> {
+    terminated(tag(name), not(peek(word)))
+}
+
+fn term(input: &str) -> IResult<&str, Term> {
+    alt((
+        map(map_res(digit1, |d: &str| d.parse::<u64>()), Term::Count),
+        map(
+            verify(word, |word: &str| !KEYWORDS.contains(&word)),
+            |color: &str| Term::Color(color.to_string()),
+        ),
+    ))(input)
+}
+
+fn sum(input: &str) -> IResult<&str, Sum> {
+    map(separated_list1(ws(char('+')), term), Sum)(input)
+}
+
+fn comparison(input: &str) -> IResult<&str, Comparison> {
+    alt((
+        map(alt((tag("<="), tag("≤"))), |_| Comparison::LessOrEqual),
+        map(alt((tag(">="), tag("≥"))), |_| Comparison::GreaterOrEqual),
+        map(alt((tag("!="), tag("≠"))), |_| Comparison::NotEqual),
+        map(alt((tag("=="), tag("="))), |_| Comparison::Equal),
+        map(tag("<"), |_| Comparison::Less),
+        map(tag(">"), |_| Comparison::Greater),
+    ))(input)
+}
+
+fn compare(input: &str) -> IResult<&str, Query> {
+    map(tuple((sum, ws(comparison), sum)), |(left, op, right)| {
+        Query::Compare(left, op, right)
+    })(input)
+}
+
+fn unary(input: &str) -> IResult<&str, Query> {
+    alt((
+        map(
+            preceded(pair(keyword("not"), multispace0), unary),
+            |query| Query::Not(Box::new(query)),
+        ),
+        delimited(ws(char('(')), query, ws(char(')'))),
+        compare,
+    ))(input)
+}
+
+fn and(input: &str) -> IResult<&str, Query> {
+    let (input, first) = unary(input)?;
+    let (input, rest) = many0(preceded(ws(keyword("and")), unary))(input)?;
+    Ok((
+        input,
+        rest.into_iter().fold(first, |acc, query| {
+            Query::And(Box::new(acc), Box::new(query))
+        }),
+    ))
+}
+
+fn query(input: &str) -> IResult<&str, Query> {
+    let (input, first) = and(input)?;
+    let (input, rest) = many0(preceded(ws(keyword("or")), and))(input)?;
+    Ok((
+        input,
+        rest.into_iter().fold(first, |acc, query| {
+            Query::Or(Box::new(acc), Box::new(query))
+        }),
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{parser, test::INPUT};
+
+    fn matching_ids(query: &str) -> Vec<u32> {
+        let games = parser::parse(INPUT).expect("Must parse");
+        let query = query.parse::<Query>().expect("Query must parse");
+        query.filter(&games).map(|game| game.id).collect()
+    }
+
+    #[test]
+    fn parse_query() {
+        let expected = Query::Or(
+            Box::new(Query::And(
+                Box::new(Query::Compare(
+                    Sum(vec![Term::Color("red".to_string())]),
+                    Comparison::LessOrEqual,
+                    Sum(vec![Term::Count(12)]),
+                )),
+                Box::new(Query::Not(Box::new(Query::Compare(
+                    Sum(vec![
+                        Term::Color("green".to_string()),
+                        Term::Color("blue".to_string()),
+                    ]),
+                    Comparison::Greater,
+                    Sum(vec![Term::Count(25)]),
+                )))),
+            )),
+            Box::new(Query::Compare(
+                Sum(vec![Term::Color("blue".to_string())]),
+                Comparison::Equal,
+                Sum(vec![Term::Color("red".to_string())]),
+            )),
+        );
+        let result = "red <= 12 and not (green+blue > 25) or blue == red"
+            .parse::<Query>()
+            .expect("Query must parse");
+        assert_eq!(result, expected);
+        let unspaced = "red<=12 and not(green+blue>25) or blue==red"
+            .parse::<Query>()
+            .expect("Query must parse");
+        assert_eq!(unspaced, expected);
+        // Without a word boundary `not` is part of a color name.
+        assert!(matches!(
+            "notred < 3".parse::<Query>(),
+            Ok(Query::Compare(..))
+        ));
+    }
+
+    #[test]
+    fn evaluate_queries() {
+        assert_eq!(
+            matching_ids("red <= 12 and green <= 13 and blue <= 14"),
+            vec![1, 2, 5]
+        );
+        assert_eq!(
+            matching_ids("red ≤ 12 and green + blue ≤ 25"),
+            vec![1, 2, 5]
+        );
+        assert_eq!(matching_ids("blue > red"), vec![1, 2, 4]);
+        assert_eq!(matching_ids("not blue > red or red >= 20"), vec![3, 5]);
+        assert_eq!(matching_ids("purple = 0 and red + 1 < 3"), vec![2]);
+    }
+
+    #[test]
+    fn reject_bad_queries() {
+        assert_eq!(
+            "red <= 12 and".parse::<Query>(),
+            Err(QueryError {
+                column: 11,
+                query: "red <= 12 and".to_string()
+            })
+        );
+        assert!("and < 3".parse::<Query>().is_err());
+        assert!("red".parse::<Query>().is_err());
+        assert!("(red < 3".parse::<Query>().is_err());
+    }
+}
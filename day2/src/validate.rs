@@ -0,0 +1,160 @@
+use std::{collections::HashMap, fmt::Display};
+
+use crate::Game;
+
+/// Problems with the sequence of game IDs. `index` is the position of the offending game in the
+/// input, counting from zero.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IdDiagnostic {
+    Duplicate {
+        id: u32,
+        first_index: usize,
+        index: usize,
+    },
+    NonMonotonic {
+        id: u32,
+        previous: u32,
+        index: usize,
+    },
+    /// Every ID from `from` to `to`, inclusive, is absent.
+    Missing { from: u32, to: u32 },
+}
+
+impl Display for IdDiagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Duplicate {
+                id,
+                first_index,
+                index,
+            } => write!(
+                f,
+                "game {} at position {} was already seen at position {}",
+                id, index, first_index
+            ),
+            Self::NonMonotonic {
+                id,
+                previous,
+                index,
+            } => write!(
+                f,
+                "game {} at position {} comes after game {}",
+                id, index, previous
+            ),
+            Self::Missing { from, to } if from == to => write!(f, "game {} is missing", from),
+            Self::Missing { from, to } => write!(f, "games {} to {} are missing", from, to),
+        }
+    }
+}
+
+/// Checks that game IDs count up from 1 without gaps, repeats or reordering.
+pub fn validate_ids(games: &[Game]) -> Vec<IdDiagnostic> {
+    let mut diagnostics = Vec::new();
+    let mut seen: HashMap<u32, usize> = HashMap::new();
+    let mut previous: Option<u32> = None;
+    for (index, game) in games.iter().enumerate() {
+        let id = game.id;
+        if let Some(&first_index) = seen.get(&id) {
+            diagnostics.push(IdDiagnostic::Duplicate {
+                id,
+                first_index,
+                index,
+            });
+        } else {
+            seen.insert(id, index);
+            if let Some(previous) = previous.filter(|&previous| id < previous) {
+                diagnostics.push(IdDiagnostic::NonMonotonic {
+                    id,
+                    previous,
+                    index,
+                });
+            }
+        }
+        previous = Some(id);
+    }
+    let mut ids = seen.into_keys().collect::<Vec<_>>();
+    ids.sort_unstable();
+    let mut next = 1;
+    for id in ids {
+        if id > next {
+            diagnostics.push(IdDiagnostic::Missing {
+                from: next,
+                to: id - 1,
+            });
+        }
+        next = next.max(id.saturating_add(1));
+    }
+    diagnostics
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser;
+
+    #[test]
+    fn valid_ids() {
+        let games =
+            parser::parse("Game 1: 1 red\nGame 2: 1 red\nGame 3: 1 red").expect("Must parse");
+        assert_eq!(validate_ids(&games), vec![]);
+    }
+
+    #[test]
+    fn invalid_ids() {
+        let games = parser::parse(
+            "Game 2: 1 red
+Game 5: 1 red
+Game 4: 1 red
+Game 5: 1 red",
+        )
+        .expect("Must parse");
+        assert_eq!(
+            validate_ids(&games),
+            vec![
+                IdDiagnostic::NonMonotonic {
+                    id: 4,
+                    previous: 5,
+                    index: 2
+                },
+                IdDiagnostic::Duplicate {
+                    id: 5,
+                    first_index: 1,
+                    index: 3
+                },
+                IdDiagnostic::Missing { from: 1, to: 1 },
+                IdDiagnostic::Missing { from: 3, to: 3 },
+            ]
+        );
+    }
+
+    #[test]
+    fn huge_id_is_one_gap() {
+        let games = parser::parse("Game 4294967295: 1 red").expect("Must parse");
+        let diagnostics = validate_ids(&games);
+        assert_eq!(
+            diagnostics,
+            vec![IdDiagnostic::Missing {
+                from: 1,
+                to: 4294967294,
+            }]
+        );
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "games 1 to 4294967294 are missing"
+        );
+
+        let games = parser::parse("Game 3: 1 red\nGame 1: 1 red\nGame 7: 1 red\nGame 8: 1 red")
+            .expect("Must parse");
+        let missing = validate_ids(&games)
+            .into_iter()
+            .filter(|diagnostic| matches!(diagnostic, IdDiagnostic::Missing { .. }))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            missing,
+            vec![
+                IdDiagnostic::Missing { from: 2, to: 2 },
+                IdDiagnostic::Missing { from: 4, to: 6 },
+            ]
+        );
+    }
+}
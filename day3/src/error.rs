@@ -0,0 +1,15 @@
+use thiserror::Error;
+
+use crate::grid::GridError;
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum SchematicError {
+    #[error("number {digits} at ({x}, {y}) does not fit in a u64")]
+    NumberTooLarge { x: usize, y: usize, digits: String },
+    #[error("({x}, {y}) is off the grid")]
+    OutOfBounds { x: usize, y: usize },
+    #[error("total does not fit in a u64")]
+    Overflow,
+    #[error(transparent)]
+    Grid(#[from] GridError),
+}
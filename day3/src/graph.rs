@@ -0,0 +1,170 @@
+//! The bipartite graph of parts and the symbols they touch.
+
+use std::{collections::HashMap, fmt::Display};
+
+use petgraph::{
+    dot::{Config, Dot},
+    graph::{NodeIndex, UnGraph},
+    unionfind::UnionFind,
+};
+
+use crate::{Part, Schematic};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Node {
+    Part(Part),
+    Symbol {
+        symbol: char,
+        position: (usize, usize),
+    },
+}
+
+impl Display for Node {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Node::Part(part) => {
+                let (x, y) = part.location.topleft();
+                write!(f, "{} @ ({x}, {y})", part.id)
+            }
+            Node::Symbol {
+                symbol,
+                position: (x, y),
+            } => write!(f, "{symbol} @ ({x}, {y})"),
+        }
+    }
+}
+
+/// Parts and symbols as nodes, with an edge wherever a part is adjacent to a symbol. Every
+/// symbol is a node, even one touching no parts.
+#[derive(Debug, Clone)]
+pub struct PartGraph {
+    graph: UnGraph<Node, ()>,
+}
+
+impl PartGraph {
+    pub fn new(schematic: &Schematic) -> Self {
+        let mut graph = UnGraph::new_undirected();
+        let mut part_nodes = HashMap::new();
+        for (position, symbol) in schematic.symbols() {
+            let symbol_node = graph.add_node(Node::Symbol { symbol, position });
+            for part in schematic.numbers.around(position) {
+                let part_node = *part_nodes
+                    .entry(part)
+                    .or_insert_with(|| graph.add_node(Node::Part(part)));
+                graph.add_edge(symbol_node, part_node, ());
+            }
+        }
+        Self { graph }
+    }
+
+    pub fn graph(&self) -> &UnGraph<Node, ()> {
+        &self.graph
+    }
+
+    /// Groups of nodes connected through shared parts, each in the order the nodes were added.
+    pub fn components(&self) -> Vec<Vec<&Node>> {
+        let mut sets = UnionFind::new(self.graph.node_count());
+        for edge in self.graph.raw_edges() {
+            sets.union(edge.source().index(), edge.target().index());
+        }
+        let mut component_of = HashMap::new();
+        let mut components: Vec<Vec<&Node>> = Vec::new();
+        for node in self.graph.node_indices() {
+            let component = *component_of
+                .entry(sets.find(node.index()))
+                .or_insert_with(|| {
+                    components.push(Vec::new());
+                    components.len() - 1
+                });
+            components[component].push(&self.graph[node]);
+        }
+        components
+    }
+
+    /// Every symbol along with how many parts it touches.
+    pub fn symbol_degrees(&self) -> impl Iterator<Item = (char, (usize, usize), usize)> + '_ {
+        self.graph
+            .node_indices()
+            .filter_map(|node| match self.graph[node] {
+                Node::Symbol { symbol, position } => Some((symbol, position, self.degree(node))),
+                Node::Part(_) => None,
+            })
+    }
+
+    /// Parts adjacent to more than one symbol, along with how many.
+    pub fn shared_parts(&self) -> impl Iterator<Item = (Part, usize)> + '_ {
+        self.graph
+            .node_indices()
+            .filter_map(|node| match self.graph[node] {
+                Node::Part(part) => Some((part, self.degree(node))),
+                Node::Symbol { .. } => None,
+            })
+            .filter(|&(_, degree)| degree > 1)
+    }
+
+    fn degree(&self, node: NodeIndex) -> usize {
+        self.graph.neighbors(node).count()
+    }
+
+    /// The graph in Graphviz DOT format, labelled with each node's `Display`.
+    pub fn to_dot(&self) -> String {
+        let dot = Dot::with_attr_getters(
+            &self.graph,
+            &[Config::EdgeNoLabel, Config::NodeNoLabel],
+            &|_, _| String::new(),
+            // Debug-quoting the label escapes any `"` or `\` symbols for DOT.
+            &|_, (_, node)| format!("label = {:?}", node.to_string()),
+        );
+        format!("{dot:?}")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn components_degrees_and_shared_parts() {
+        let data = "\
+1.....
+*2#...
+......
+..3..4
+.....$
+";
+        let graph = Schematic::new(data).expect("numbers fit").graph();
+        let components = graph
+            .components()
+            .into_iter()
+            .map(|nodes| nodes.iter().map(ToString::to_string).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            components,
+            vec![
+                vec!["* @ (0, 1)", "1 @ (0, 0)", "2 @ (1, 1)", "# @ (2, 1)"],
+                vec!["$ @ (5, 4)", "4 @ (5, 3)"],
+            ]
+        );
+        assert_eq!(
+            graph.symbol_degrees().collect::<Vec<_>>(),
+            vec![('*', (0, 1), 2), ('#', (2, 1), 1), ('$', (5, 4), 1)]
+        );
+        let shared = graph
+            .shared_parts()
+            .map(|(part, degree)| (part.id, degree))
+            .collect::<Vec<_>>();
+        assert_eq!(shared, vec![(2, 2)]);
+    }
+
+    #[test]
+    fn dot_export() {
+        let dot = Schematic::new("1*\n")
+            .expect("numbers fit")
+            .graph()
+            .to_dot();
+        assert!(dot.starts_with("graph {"), "{dot}");
+        assert!(dot.contains("label = \"* @ (1, 0)\""), "{dot}");
+        assert!(dot.contains("label = \"1 @ (0, 0)\""), "{dot}");
+        assert!(dot.contains("0 -- 1"), "{dot}");
+    }
+}
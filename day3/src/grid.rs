@@ -0,0 +1,233 @@
+use std::{
+    fmt::Display,
+    ops::{Index, IndexMut},
+    str::FromStr,
+};
+
+use thiserror::Error;
+
+use crate::coords;
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum GridError {
+    #[error("row {row} has {found} cells, expected {expected}")]
+    Ragged {
+        row: usize,
+        expected: usize,
+        found: usize,
+    },
+}
+
+/// A rectangular grid stored row-major in a single `Vec`. Coordinates are `(x, y)` with the
+/// origin in the top left.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Grid<T> {
+    width: usize,
+    height: usize,
+    cells: Vec<T>,
+}
+
+impl<T> Grid<T> {
+    /// Fails if the rows are not all the same length.
+    pub fn from_rows(rows: Vec<Vec<T>>) -> Result<Self, GridError> {
+        let height = rows.len();
+        let width = rows.first().map_or(0, Vec::len);
+        let mut cells = Vec::with_capacity(width * height);
+        for (row, cells_in_row) in rows.into_iter().enumerate() {
+            if cells_in_row.len() != width {
+                return Err(GridError::Ragged {
+                    row,
+                    expected: width,
+                    found: cells_in_row.len(),
+                });
+            }
+            cells.extend(cells_in_row);
+        }
+        Ok(Self {
+            width,
+            height,
+            cells,
+        })
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn contains(&self, (x, y): (usize, usize)) -> bool {
+        x < self.width && y < self.height
+    }
+
+    fn offset(&self, (x, y): (usize, usize)) -> Option<usize> {
+        self.contains((x, y)).then_some(y * self.width + x)
+    }
+
+    pub fn get(&self, coord: (usize, usize)) -> Option<&T> {
+        self.cells.get(self.offset(coord)?)
+    }
+
+    pub fn get_mut(&mut self, coord: (usize, usize)) -> Option<&mut T> {
+        let offset = self.offset(coord)?;
+        self.cells.get_mut(offset)
+    }
+
+    pub fn row(&self, y: usize) -> Option<&[T]> {
+        (y < self.height).then(|| &self.cells[y * self.width..(y + 1) * self.width])
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = &[T]> {
+        (0..self.height).map(|y| &self.cells[y * self.width..(y + 1) * self.width])
+    }
+
+    pub fn column(&self, x: usize) -> impl Iterator<Item = &T> {
+        let height = if x < self.width { self.height } else { 0 };
+        (0..height).map(move |y| &self.cells[y * self.width + x])
+    }
+
+    pub fn columns(&self) -> impl Iterator<Item = impl Iterator<Item = &T>> {
+        (0..self.width).map(|x| self.column(x))
+    }
+
+    /// Every cell along with its coordinate, row by row.
+    pub fn iter(&self) -> impl Iterator<Item = ((usize, usize), &T)> {
+        let width = self.width;
+        self.cells
+            .iter()
+            .enumerate()
+            .map(move |(offset, value)| ((offset % width, offset / width), value))
+    }
+
+    /// The in-bounds coordinates directly above, right of, below and left of `coord`.
+    pub fn neighbours4(&self, coord: (usize, usize)) -> impl Iterator<Item = (usize, usize)> + '_ {
+        [
+            coords::north_from(coord),
+            coords::east_from(coord),
+            coords::south_from(coord),
+            coords::west_from(coord),
+        ]
+        .into_iter()
+        .flatten()
+        .filter(|&coord| self.contains(coord))
+    }
+
+    /// The in-bounds coordinates touching `coord`, diagonals included.
+    pub fn neighbours8(&self, coord: (usize, usize)) -> impl Iterator<Item = (usize, usize)> + '_ {
+        coords::adjacencies_to(coord)
+            .into_iter()
+            .filter(|&coord| self.contains(coord))
+    }
+}
+
+impl<T: Clone> Grid<T> {
+    pub fn filled(width: usize, height: usize, value: T) -> Self {
+        Self {
+            width,
+            height,
+            cells: vec![value; width * height],
+        }
+    }
+
+    /// Builds a grid as wide as the longest row, filling out shorter rows with `fill`.
+    pub fn from_rows_padded(rows: Vec<Vec<T>>, fill: T) -> Self {
+        let width = rows.iter().map(Vec::len).max().unwrap_or(0);
+        let rows = rows
+            .into_iter()
+            .map(|mut row| {
+                row.resize(width, fill.clone());
+                row
+            })
+            .collect();
+        Self::from_rows(rows).expect("every row was padded to the same width")
+    }
+}
+
+impl<T> Index<(usize, usize)> for Grid<T> {
+    type Output = T;
+
+    fn index(&self, coord: (usize, usize)) -> &Self::Output {
+        self.get(coord)
+            .unwrap_or_else(|| panic!("{:?} is outside the grid", coord))
+    }
+}
+
+impl<T> IndexMut<(usize, usize)> for Grid<T> {
+    fn index_mut(&mut self, coord: (usize, usize)) -> &mut Self::Output {
+        self.get_mut(coord)
+            .unwrap_or_else(|| panic!("{:?} is outside the grid", coord))
+    }
+}
+
+impl FromStr for Grid<char> {
+    type Err = GridError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_rows(s.lines().map(|line| line.chars().collect()).collect())
+    }
+}
+
+impl Display for Grid<char> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for row in self.rows() {
+            writeln!(f, "{}", row.iter().collect::<String>())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_grid() {
+        let grid = "abc\ndef".parse::<Grid<char>>().expect("Must parse");
+        assert_eq!((grid.width(), grid.height()), (3, 2));
+        assert_eq!(grid[(2, 1)], 'f');
+        assert_eq!(grid.get((3, 0)), None);
+        assert_eq!(grid.row(1), Some(&['d', 'e', 'f'][..]));
+        assert_eq!(grid.column(1).collect::<String>(), "be");
+        assert_eq!(
+            grid.columns()
+                .map(|column| column.collect::<String>())
+                .collect::<Vec<_>>(),
+            vec!["ad", "be", "cf"]
+        );
+        assert_eq!(grid.to_string(), "abc\ndef\n");
+        assert_eq!(
+            "abc\nde".parse::<Grid<char>>(),
+            Err(GridError::Ragged {
+                row: 1,
+                expected: 3,
+                found: 2
+            })
+        );
+    }
+
+    #[test]
+    fn pad_ragged_rows() {
+        let grid = Grid::from_rows_padded(vec![vec![1], vec![2, 3, 4], vec![]], 0);
+        assert_eq!(
+            grid.rows().collect::<Vec<_>>(),
+            vec![&[1, 0, 0], &[2, 3, 4], &[0, 0, 0]]
+        );
+    }
+
+    #[test]
+    fn neighbours() {
+        let grid = "abc\ndef\nghi".parse::<Grid<char>>().expect("Must parse");
+        let mut corner = grid.neighbours8((0, 0)).collect::<Vec<_>>();
+        corner.sort();
+        assert_eq!(corner, vec![(0, 1), (1, 0), (1, 1)]);
+        assert_eq!(grid.neighbours8((1, 1)).count(), 8);
+        assert_eq!(
+            grid.neighbours4((2, 2))
+                .map(|coord| grid[coord])
+                .collect::<String>(),
+            "fh"
+        );
+    }
+}
@@ -1,44 +1,99 @@
-#![feature(impl_trait_in_assoc_type)]
+pub mod error;
+pub mod graph;
+pub mod grid;
+mod parallel;
+pub mod render;
+pub mod rules;
+pub mod scan;
+pub mod stats;
+pub mod validate;
 
-use itertools::Itertools;
 use std::collections::HashSet;
 
-trait Adjacence<T>
+use error::SchematicError;
+use grid::Grid;
+use rules::SchematicRules;
+use scan::{Diff, Numbers};
+
+pub trait Adjacence<T>
 where
     T: PartialEq + Eq,
 {
+    /// Whether any cell around `other` falls on `self`.
     fn is_adjacent_to<O: Adjacence<T>>(&self, other: &O) -> bool;
+    /// The cells touching `self` (diagonals included), skipping any that would fall off the
+    /// top or left of the coordinate space.
     fn adjacencies(&self) -> impl Iterator<Item = T>;
 }
 
+/// An inclusive rectangle of cells.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-struct BoundingBox {
+pub struct BoundingBox {
     topleft: (usize, usize),
     bottomright: (usize, usize),
 }
 
+impl BoundingBox {
+    /// The corners may be given in any order.
+    pub fn new((x1, y1): (usize, usize), (x2, y2): (usize, usize)) -> Self {
+        Self {
+            topleft: (x1.min(x2), y1.min(y2)),
+            bottomright: (x1.max(x2), y1.max(y2)),
+        }
+    }
+
+    pub fn topleft(&self) -> (usize, usize) {
+        self.topleft
+    }
+
+    pub fn bottomright(&self) -> (usize, usize) {
+        self.bottomright
+    }
+
+    pub fn contains(&self, (x, y): (usize, usize)) -> bool {
+        (self.topleft.0..=self.bottomright.0).contains(&x)
+            && (self.topleft.1..=self.bottomright.1).contains(&y)
+    }
+
+    /// Like `adjacencies`, but also skipping cells past the right or bottom edge of a grid of
+    /// the given size.
+    pub fn adjacencies_within(
+        &self,
+        width: usize,
+        height: usize,
+    ) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.adjacencies()
+            .filter(move |&(x, y)| x < width && y < height)
+    }
+}
+
 impl Adjacence<(usize, usize)> for BoundingBox {
     fn is_adjacent_to<O: Adjacence<(usize, usize)>>(&self, other: &O) -> bool {
-        let ((x1, y1), (x2, y2)) = (self.topleft, self.bottomright);
-        let bounds = (x1..=x2).zip(y1..=y2).collect::<HashSet<_>>();
-        other.adjacencies().any(|pos| bounds.contains(&pos))
+        other.adjacencies().any(|pos| self.contains(pos))
     }
 
     fn adjacencies(&self) -> impl Iterator<Item = (usize, usize)> {
-        let (x1, x2) = (self.topleft.0, self.bottomright.0);
-        let (y1, y2) = (self.topleft.1, self.bottomright.1);
-        (x1 - 1..=x2 + 1)
-            .map(move |x| (x - 1, y1)) // top side
-            .chain(
-                (y1 - 1..=y2 + 1).map(move |y| (x2 + 1, y)), // right side
-            )
-            .chain(
-                (x1 - 1..=x2 + 1).map(move |x| (x, y2 - 1)), // bottom side
-            )
-            .chain(
-                (y1 - 1..=y2 + 1).map(move |y| (x1 - 1, y)), // Left side
-            )
-            .unique()
+        let ((x1, y1), (x2, y2)) = (self.topleft, self.bottomright);
+        let left = x1.checked_sub(1);
+        let right = x2.checked_add(1);
+        let above = y1.checked_sub(1);
+        let below = y2.checked_add(1);
+        // The top and bottom sides include the corners; the left and right sides don't.
+        let columns = left.unwrap_or(x1)..=right.unwrap_or(x2);
+        let top = above
+            .into_iter()
+            .flat_map(move |y| columns.clone().map(move |x| (x, y)));
+        let columns = left.unwrap_or(x1)..=right.unwrap_or(x2);
+        let bottom = below
+            .into_iter()
+            .flat_map(move |y| columns.clone().map(move |x| (x, y)));
+        let right_side = right
+            .into_iter()
+            .flat_map(move |x| (y1..=y2).map(move |y| (x, y)));
+        let left_side = left
+            .into_iter()
+            .flat_map(move |x| (y1..=y2).map(move |y| (x, y)));
+        top.chain(right_side).chain(bottom).chain(left_side)
     }
 }
 
@@ -92,162 +147,279 @@ mod coords {
         .flatten()
         .collect()
     }
-
-    pub fn get_value_on_grid<V>(grid: &[Vec<V>], (coord_x, coord_y): (usize, usize)) -> Option<&V> {
-        grid.get(coord_y)?.get(coord_x)
-    }
-
-    pub fn get_optional_value_on_grid<V>(
-        grid: &[Vec<V>],
-        coord: Option<(usize, usize)>,
-    ) -> Option<&V> {
-        get_value_on_grid(grid, coord?)
-    }
 }
 
 #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
 pub struct Part {
-    id: u32,
+    id: u64,
     location: BoundingBox,
 }
 impl Part {
-    pub fn from_coordinate_on_grid(grid: &[Vec<char>], coordinate: (usize, usize)) -> Option<Self> {
-        let mut final_number_acc = String::new();
-        let val = *coords::get_value_on_grid(grid, coordinate)?;
-        let mut topleft = coordinate;
-        let mut bottomright = coordinate;
-        if val.is_numeric() {
-            final_number_acc.push(val);
-            // Add from the left
-            let mut cur_pos = coordinate;
-            while let Some(left) =
-                coords::get_optional_value_on_grid(grid, coords::west_from(cur_pos))
-            {
-                if left.is_numeric() {
-                    final_number_acc.insert(0, *left);
-                    cur_pos = coords::west_from(cur_pos)?;
-                    topleft = cur_pos;
-                } else {
-                    break;
-                }
-            }
-            // Add from the right
-            cur_pos = coordinate;
-            while let Some(right) =
-                coords::get_optional_value_on_grid(grid, coords::east_from(cur_pos))
-            {
-                if right.is_numeric() {
-                    final_number_acc.push(*right);
-                    cur_pos = coords::east_from(cur_pos)?;
-                    bottomright = cur_pos;
-                } else {
-                    break;
-                }
-            }
-        }
+    pub fn id(&self) -> u64 {
+        self.id
+    }
 
-        if final_number_acc.is_empty() {
-            None
-        } else {
-            let id = final_number_acc
-                .parse::<u32>()
-                .expect("All numerics should be okay to parse");
-            let location = BoundingBox {
-                topleft,
-                bottomright,
-            };
-            Some(Part { id, location })
+    /// The cells the number's digits cover.
+    pub fn location(&self) -> BoundingBox {
+        self.location
+    }
+
+    /// The number whose digits cover `coordinate`, if there is one. Only ASCII digits count.
+    pub fn from_coordinate_on_grid(
+        grid: &Grid<char>,
+        (x, y): (usize, usize),
+    ) -> Result<Option<Self>, SchematicError> {
+        let Some(row) = grid.row(y) else {
+            return Ok(None);
+        };
+        if !row.get(x).is_some_and(char::is_ascii_digit) {
+            return Ok(None);
         }
+        let first = row[..x]
+            .iter()
+            .rposition(|val| !val.is_ascii_digit())
+            .map_or(0, |before| before + 1);
+        let end = row[x..]
+            .iter()
+            .position(|val| !val.is_ascii_digit())
+            .map_or(row.len(), |after| x + after);
+        Self::parse(&row[first..end], (first, y)).map(Some)
+    }
+
+    /// Reads a run of ASCII digits starting at `(x, y)`.
+    pub(crate) fn parse(digits: &[char], (x, y): (usize, usize)) -> Result<Self, SchematicError> {
+        let id = digits
+            .iter()
+            .try_fold(0u64, |acc, digit| {
+                acc.checked_mul(10)?
+                    .checked_add(u64::from(digit.to_digit(10)?))
+            })
+            .ok_or_else(|| SchematicError::NumberTooLarge {
+                x,
+                y,
+                digits: digits.iter().collect(),
+            })?;
+        let location = BoundingBox::new((x, y), (x + digits.len() - 1, y));
+        Ok(Part { id, location })
     }
 }
 
 pub struct Schematic {
-    grid: Vec<Vec<char>>,
+    grid: Grid<char>,
+    numbers: Numbers,
+    /// Numbers touching a symbol, in the order the first of their symbols is found.
     parts: Vec<Part>,
+    is_part: HashSet<Part>,
+    rules: SchematicRules,
 }
 
-#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
 pub struct Gear {
-    adjacent_parts: [Part; 2],
+    position: (usize, usize),
+    adjacent_parts: Vec<Part>,
 }
 
 impl Gear {
-    pub fn gear_ratio(&self) -> u64 {
-        self.adjacent_parts[0].id as u64 * self.adjacent_parts[1].id as u64
+    /// The product of the ids of every adjacent part.
+    pub fn gear_ratio(&self) -> Result<u64, SchematicError> {
+        self.adjacent_parts
+            .iter()
+            .try_fold(1u64, |acc, part| acc.checked_mul(part.id))
+            .ok_or(SchematicError::Overflow)
+    }
+
+    /// Where the gear symbol is.
+    pub fn position(&self) -> (usize, usize) {
+        self.position
+    }
+
+    pub fn parts(&self) -> &[Part] {
+        &self.adjacent_parts
     }
 }
 
+/// The rows of `input`, each with the `\r` of a CRLF line ending still on it. Blank lines at
+/// the end of the input aren't rows. Shared by the reader and `validate_input` so that they agree.
+pub(crate) fn raw_rows(input: &str) -> Vec<&str> {
+    let mut rows = input.split('\n').collect::<Vec<_>>();
+    while rows
+        .last()
+        .is_some_and(|row| row.trim_end_matches('\r').is_empty())
+    {
+        rows.pop();
+    }
+    rows
+}
+
 impl Schematic {
-    pub fn new(input: &str) -> Self {
-        let grid: Vec<Vec<char>> = input.lines().map(|line| line.chars().collect()).collect();
-        // Gather the non-empty, non-numeric spaces
-        let symbols = grid
-            .iter()
-            .enumerate()
-            .flat_map(|(y, row)| {
-                row.iter()
-                    .enumerate()
-                    .filter_map(|(x, val)| match val {
-                        '.' => None,
-                        val if val.is_numeric() => None,
-                        val => Some(((x, y), val)),
-                    })
-                    .collect::<Vec<_>>()
-            })
+    /// Fails if the rows differ in length or any number is too large for a `u64`.
+    pub fn new(input: &str) -> Result<Self, SchematicError> {
+        Self::with_rules(input, SchematicRules::default())
+    }
+
+    pub fn with_rules(input: &str, rules: SchematicRules) -> Result<Self, SchematicError> {
+        let grid = Self::read_grid(input, &rules)?;
+        let numbers = Numbers::new(&grid)?;
+        let mut is_part = HashSet::new();
+        let mut parts = Vec::new();
+        for (pos, _) in grid.iter().filter(|(_, val)| rules.is_symbol(**val)) {
+            for part in numbers.around(pos) {
+                if is_part.insert(part) {
+                    parts.push(part);
+                }
+            }
+        }
+
+        Ok(Self {
+            grid,
+            numbers,
+            parts,
+            is_part,
+            rules,
+        })
+    }
+
+    fn read_grid(input: &str, rules: &SchematicRules) -> Result<Grid<char>, SchematicError> {
+        let rows = raw_rows(input)
+            .into_iter()
+            .map(|row| row.strip_suffix('\r').unwrap_or(row).chars().collect())
+            .collect();
+        if rules.pad_ragged {
+            Ok(Grid::from_rows_padded(rows, rules.blank))
+        } else {
+            Ok(Grid::from_rows(rows)?)
+        }
+    }
+
+    /// Changes a single cell, re-reading only the numbers within one step of it. Returns the
+    /// parts lost and gained. Parts gained this way are listed after all the others. If the
+    /// change would make a number too large, the schematic is left as it was.
+    pub fn set_cell(&mut self, coord: (usize, usize), val: char) -> Result<Diff, SchematicError> {
+        let (x, y) = coord;
+        let cell = self
+            .grid
+            .get_mut(coord)
+            .ok_or(SchematicError::OutOfBounds { x, y })?;
+        let previous = std::mem::replace(cell, val);
+        if previous == val {
+            return Ok(Diff::default());
+        }
+
+        // Only numbers in the 3x3 block around the cell can gain or lose a symbol, and any
+        // number re-read by the rescan overlaps that block.
+        let block = |numbers: &Numbers| {
+            let mut block = numbers.around(coord);
+            match numbers.at(coord) {
+                Some(number) if !block.contains(&number) => block.push(number),
+                _ => {}
+            }
+            block
+        };
+        let before = block(&self.numbers)
+            .into_iter()
+            .filter(|number| self.is_part.contains(number))
             .collect::<Vec<_>>();
-        let parts = symbols
+        if let Err(err) = self.numbers.rescan(&self.grid, coord) {
+            self.grid[coord] = previous;
+            return Err(err);
+        }
+        let after = block(&self.numbers)
             .into_iter()
-            .flat_map(|(pos, _)| {
-                pos.adjacencies()
-                    .filter_map(|adj| Part::from_coordinate_on_grid(&grid, adj))
-                    .collect::<Vec<_>>()
-            })
-            .unique()
+            .filter(|number| self.touches_symbol(number))
             .collect::<Vec<_>>();
 
-        Self { grid, parts }
+        let removed = before
+            .iter()
+            .filter(|part| !after.contains(part))
+            .copied()
+            .collect::<Vec<_>>();
+        let mut added = after
+            .iter()
+            .filter(|part| !before.contains(part))
+            .copied()
+            .collect::<Vec<_>>();
+        added.sort_by_key(|part| (part.location.topleft().1, part.location.topleft().0));
+        for part in &removed {
+            self.is_part.remove(part);
+        }
+        if !removed.is_empty() {
+            self.parts.retain(|part| !removed.contains(part));
+        }
+        self.is_part.extend(added.iter().copied());
+        self.parts.extend(added.iter().copied());
+        Ok(Diff { removed, added })
+    }
+
+    fn touches_symbol(&self, number: &Part) -> bool {
+        number
+            .location
+            .adjacencies_within(self.grid.width(), self.grid.height())
+            .any(|pos| self.rules.is_symbol(self.grid[pos]))
+    }
+
+    /// Every symbol and where it is, row by row.
+    pub fn symbols(&self) -> impl Iterator<Item = ((usize, usize), char)> + '_ {
+        self.grid
+            .iter()
+            .filter(|(_, val)| self.rules.is_symbol(**val))
+            .map(|(pos, val)| (pos, *val))
+    }
+
+    pub fn graph(&self) -> graph::PartGraph {
+        graph::PartGraph::new(self)
+    }
+
+    pub fn rules(&self) -> &SchematicRules {
+        &self.rules
     }
 
     pub fn find_gears(&self) -> Vec<Gear> {
+        self.gears().collect()
+    }
+
+    /// Every gear, row by row.
+    pub fn gears(&self) -> impl Iterator<Item = Gear> + '_ {
         self.grid
             .iter()
-            .enumerate()
-            .flat_map(|(y, row)| {
-                row.iter()
-                    .enumerate()
-                    .filter_map(|(x, val)| match val {
-                        '*' => Some(((x, y), val)),
-                        _ => None,
-                    })
-                    .collect::<Vec<_>>()
-            })
-            .filter_map(|(coord, _)| {
-                let adjacent_parts = coord
-                    .adjacencies()
-                    .filter_map(|adj| Part::from_coordinate_on_grid(&self.grid, adj))
-                    .unique()
-                    .collect::<Vec<_>>();
-
-                if adjacent_parts.len() == 2 {
-                    Some(Gear {
-                        adjacent_parts: [adjacent_parts[0], adjacent_parts[1]],
-                    })
-                } else {
-                    None
-                }
+            .filter_map(|(coord, _)| self.gear_at(coord))
+    }
+
+    fn gear_at(&self, coord: (usize, usize)) -> Option<Gear> {
+        if !self.rules.is_gear_candidate(self.grid[coord]) {
+            return None;
+        }
+        let adjacent_parts = self.numbers.around(coord);
+        self.rules
+            .gear_parts
+            .allows(adjacent_parts.len())
+            .then_some(Gear {
+                position: coord,
+                adjacent_parts,
             })
-            .collect()
     }
 
-    pub fn sum(&self) -> u32 {
-        self.parts.iter().map(|part| part.id).sum::<u32>()
+    pub fn sum(&self) -> Result<u64, SchematicError> {
+        self.parts
+            .iter()
+            .try_fold(0u64, |acc, part| acc.checked_add(part.id))
+            .ok_or(SchematicError::Overflow)
+    }
+
+    /// Numbers touching at least one symbol, in the order their first symbol is found.
+    pub fn parts(&self) -> impl Iterator<Item = &Part> {
+        self.parts.iter()
+    }
+
+    /// Numbers touching no symbol, in reading order.
+    pub fn non_parts(&self) -> impl Iterator<Item = &Part> {
+        self.numbers
+            .iter()
+            .filter(|number| !self.is_part.contains(number))
     }
 
-    pub fn parts(&self) {
-        println!(
-            "parts: {}",
-            self.parts.iter().map(|part| part.id.to_string()).join(", "),
-        )
+    /// Every number in the grid, part or not, in reading order.
+    pub fn numbers(&self) -> impl Iterator<Item = &Part> {
+        self.numbers.iter()
     }
 
     // fn get_adjacencies(&self, (x, y): (usize, usize)) -> Vec<char> {
@@ -260,10 +432,71 @@ impl Schematic {
 #[cfg(test)]
 mod test {
     use super::*;
+    use std::collections::HashSet;
+
+    /// Every in-bounds cell within one step of `bbox` but not inside it.
+    fn brute_force_ring(
+        bbox: &BoundingBox,
+        width: usize,
+        height: usize,
+    ) -> HashSet<(usize, usize)> {
+        let ((x1, y1), (x2, y2)) = (bbox.topleft(), bbox.bottomright());
+        (0..width)
+            .flat_map(|x| (0..height).map(move |y| (x, y)))
+            .filter(|&(x, y)| {
+                x + 1 >= x1 && x <= x2 + 1 && y + 1 >= y1 && y <= y2 + 1 && !bbox.contains((x, y))
+            })
+            .collect()
+    }
 
     #[test]
-    fn make_schematic() {
-        let data = "\
+    fn adjacencies_everywhere_on_grid() {
+        let (width, height) = (6, 5);
+        for (box_width, box_height) in [(1, 1), (3, 1), (1, 3), (2, 2), (6, 1), (6, 5)] {
+            for x1 in 0..=width - box_width {
+                for y1 in 0..=height - box_height {
+                    let bbox =
+                        BoundingBox::new((x1, y1), (x1 + box_width - 1, y1 + box_height - 1));
+                    let ring = bbox.adjacencies_within(width, height).collect::<Vec<_>>();
+                    let unique = ring.iter().copied().collect::<HashSet<_>>();
+                    assert_eq!(ring.len(), unique.len(), "{bbox:?} repeats a cell");
+                    assert_eq!(unique, brute_force_ring(&bbox, width, height), "{bbox:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn adjacencies_at_origin_do_not_underflow() {
+        let bbox = BoundingBox::new((0, 0), (2, 0));
+        let ring = bbox.adjacencies().collect::<HashSet<_>>();
+        let expected = [(0, 1), (1, 1), (2, 1), (3, 1), (3, 0)]
+            .into_iter()
+            .collect::<HashSet<_>>();
+        assert_eq!(ring, expected);
+    }
+
+    #[test]
+    fn adjacent_boxes_and_points() {
+        let bbox = BoundingBox::new((2, 2), (4, 3));
+        // Every cell of the ring around the box touches it.
+        for x in 1..=5 {
+            for y in 1..=4 {
+                if !bbox.contains((x, y)) {
+                    assert!(bbox.is_adjacent_to(&(x, y)), "{:?}", (x, y));
+                    assert!((x, y).is_adjacent_to(&bbox), "{:?}", (x, y));
+                }
+            }
+        }
+        assert!(!bbox.is_adjacent_to(&(0, 2)));
+        assert!(!bbox.is_adjacent_to(&(3, 5)));
+        // Boxes touching only at a corner are adjacent; boxes a cell apart are not.
+        assert!(bbox.is_adjacent_to(&BoundingBox::new((5, 4), (7, 4))));
+        assert!(!bbox.is_adjacent_to(&BoundingBox::new((6, 2), (7, 3))));
+    }
+
+    /// The puzzle's example schematic, shared by the tests of every module.
+    pub(crate) const EXAMPLE: &str = "\
 467..114..
 ...*......
 ..35..633.
@@ -275,7 +508,23 @@ mod test {
 ...$.*....
 .664.598..
 ";
-        let schematic = Schematic::new(data);
+
+    /// xorshift, so that randomised tests make the same choices on every run.
+    pub(crate) struct XorShift(pub(crate) u64);
+
+    impl XorShift {
+        /// A value in `0..bound`. `bound` must not be zero.
+        pub(crate) fn below(&mut self, bound: usize) -> usize {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0 as usize % bound
+        }
+    }
+
+    #[test]
+    fn make_schematic() {
+        let schematic = Schematic::new(EXAMPLE).expect("numbers fit");
         let expected_parts = vec![467, 35, 633, 617, 592, 664, 755, 598];
         assert_eq!(
             schematic
@@ -286,4 +535,200 @@ mod test {
             expected_parts
         );
     }
+
+    fn part_ids(schematic: &Schematic) -> Vec<u64> {
+        schematic.parts.iter().map(|part| part.id).collect()
+    }
+
+    #[test]
+    fn default_rules_match_plain_schematic() {
+        let schematic =
+            Schematic::with_rules(EXAMPLE, SchematicRules::default()).expect("numbers fit");
+        assert_eq!(
+            part_ids(&schematic),
+            part_ids(&Schematic::new(EXAMPLE).expect("numbers fit"))
+        );
+        let ratios = schematic
+            .find_gears()
+            .iter()
+            .map(|gear| gear.gear_ratio().expect("ratio fits"))
+            .collect::<Vec<_>>();
+        assert_eq!(ratios, vec![467 * 35, 755 * 598]);
+    }
+
+    #[test]
+    fn restricted_symbols_and_blank() {
+        let rules = SchematicRules {
+            blank: ' ',
+            symbols: rules::Symbols::OneOf(vec!['#', '*']),
+            ..SchematicRules::default()
+        };
+        // '.' is now an ordinary character and '+' and '$' are not symbols.
+        let schematic =
+            Schematic::with_rules(&EXAMPLE.replace('.', " "), rules).expect("numbers fit");
+        assert_eq!(part_ids(&schematic), vec![467, 35, 633, 617, 755, 598]);
+    }
+
+    #[test]
+    fn gear_part_counts() {
+        let data = "\
+2.3
+.*.
+4..
+";
+        let gears_with = |gear_parts| {
+            let rules = SchematicRules {
+                gear_parts,
+                ..SchematicRules::default()
+            };
+            Schematic::with_rules(data, rules)
+                .expect("numbers fit")
+                .find_gears()
+                .iter()
+                .map(|gear| gear.gear_ratio().expect("ratio fits"))
+                .collect::<Vec<_>>()
+        };
+        assert!(gears_with(rules::PartCount::Exactly(2)).is_empty());
+        assert_eq!(gears_with(rules::PartCount::Exactly(3)), vec![24]);
+        assert_eq!(gears_with(rules::PartCount::AtLeast(2)), vec![24]);
+        assert!(gears_with(rules::PartCount::Between(4..=8)).is_empty());
+    }
+
+    #[test]
+    fn accessors_describe_the_schematic() {
+        let schematic = Schematic::new(EXAMPLE).expect("numbers fit");
+        let non_parts = schematic.non_parts().map(Part::id).collect::<Vec<_>>();
+        assert_eq!(non_parts, vec![114, 58]);
+        assert_eq!(
+            schematic.numbers().count(),
+            schematic.parts().count() + non_parts.len()
+        );
+        let first = schematic.parts().next().expect("467 touches a symbol");
+        assert_eq!(first.id(), 467);
+        assert_eq!(first.location(), BoundingBox::new((0, 0), (2, 0)));
+        let symbols = schematic.symbols().collect::<Vec<_>>();
+        assert_eq!(symbols.len(), 6);
+        assert_eq!(symbols[1], ((6, 3), '#'));
+        let gears = schematic.gears().collect::<Vec<_>>();
+        assert_eq!(gears.len(), 2);
+        assert_eq!(gears[1].position(), (5, 8));
+        assert_eq!(
+            gears[1].parts().iter().map(Part::id).collect::<Vec<_>>(),
+            vec![755, 598]
+        );
+        assert_eq!(gears[1].gear_ratio(), Ok(755 * 598));
+    }
+
+    fn sorted_parts<'a>(parts: impl Iterator<Item = &'a Part>) -> Vec<Part> {
+        let mut parts = parts.copied().collect::<Vec<_>>();
+        parts.sort_by_key(|part| (part.location.topleft().1, part.location.topleft().0));
+        parts
+    }
+
+    #[test]
+    fn set_cell_reports_parts_gained_and_lost() {
+        let mut schematic = Schematic::new(EXAMPLE).expect("numbers fit");
+        // Dropping the '#' leaves 633 with no symbol.
+        let diff = schematic.set_cell((6, 3), '.').expect("on the grid");
+        assert_eq!(
+            diff.removed.iter().map(Part::id).collect::<Vec<_>>(),
+            vec![633]
+        );
+        assert!(diff.added.is_empty());
+        // Joining 617 to the '*' beside it makes a new part and loses the old one.
+        let diff = schematic.set_cell((3, 4), '8').expect("on the grid");
+        assert_eq!(
+            diff.removed.iter().map(Part::id).collect::<Vec<_>>(),
+            vec![617]
+        );
+        assert!(diff.added.is_empty());
+        let diff = schematic.set_cell((4, 3), '+').expect("on the grid");
+        assert_eq!(
+            diff.added.iter().map(Part::id).collect::<Vec<_>>(),
+            vec![6178]
+        );
+        assert_eq!(
+            schematic.set_cell((10, 0), '*'),
+            Err(SchematicError::OutOfBounds { x: 10, y: 0 })
+        );
+        assert_eq!(schematic.set_cell((0, 0), '4'), Ok(Diff::default()));
+    }
+
+    #[test]
+    fn set_cell_matches_full_recomputation() {
+        let mut schematic = Schematic::new(EXAMPLE).expect("numbers fit");
+        let mut rng = XorShift(0x2545_f491_4f6c_dd1d);
+        let cells = ['.', '.', '.', '1', '5', '9', '*', '#', '$'];
+        for _ in 0..2000 {
+            let coord = (rng.below(10), rng.below(10));
+            let before = sorted_parts(schematic.parts());
+            let diff = schematic
+                .set_cell(coord, cells[rng.below(cells.len())])
+                .expect("on the grid");
+            let full = Schematic::new(&schematic.grid.to_string()).expect("numbers fit");
+            assert_eq!(schematic.numbers, full.numbers);
+            assert_eq!(schematic.is_part, full.is_part);
+            let after = sorted_parts(full.parts());
+            assert_eq!(sorted_parts(schematic.parts()), after);
+            let lost = before.iter().filter(|part| !after.contains(part));
+            let gained = after.iter().filter(|part| !before.contains(part));
+            assert_eq!(sorted_parts(diff.removed.iter()), sorted_parts(lost));
+            assert_eq!(diff.added, sorted_parts(gained));
+            assert_eq!(schematic.find_gears(), full.find_gears());
+        }
+    }
+
+    #[test]
+    fn wide_parts_and_overflow() {
+        let data = "12345678901234.\n*..............";
+        let schematic = Schematic::new(data).expect("numbers fit");
+        assert_eq!(schematic.sum(), Ok(12_345_678_901_234));
+        let grid = &schematic.grid;
+        let part = Part::from_coordinate_on_grid(grid, (7, 0)).expect("number fits");
+        assert_eq!(part, schematic.parts().next().copied());
+        assert_eq!(Part::from_coordinate_on_grid(grid, (14, 0)), Ok(None));
+        assert_eq!(Part::from_coordinate_on_grid(grid, (0, 5)), Ok(None));
+
+        assert!(matches!(
+            Schematic::new("99999999999999999999"),
+            Err(SchematicError::NumberTooLarge { x: 0, y: 0, .. })
+        ));
+        // Nineteen nines fit but twenty don't, so the edit is refused.
+        let mut schematic = Schematic::new("9999999999999999999.").expect("numbers fit");
+        assert!(matches!(
+            schematic.set_cell((19, 0), '9'),
+            Err(SchematicError::NumberTooLarge { .. })
+        ));
+        assert_eq!(
+            schematic.grid.to_string().trim_end(),
+            "9999999999999999999."
+        );
+
+        let schematic = Schematic::new("4294967296*4294967296").expect("numbers fit");
+        let ratios = schematic
+            .gears()
+            .map(|gear| gear.gear_ratio())
+            .collect::<Vec<_>>();
+        assert_eq!(ratios, vec![Err(SchematicError::Overflow)]);
+    }
+
+    #[test]
+    fn ragged_rows_are_rejected_unless_padded() {
+        let data = "467.\n..*\n35..\n";
+        assert_eq!(
+            Schematic::new(data).map(|schematic| schematic.grid.width()),
+            Err(SchematicError::Grid(grid::GridError::Ragged {
+                row: 1,
+                expected: 4,
+                found: 3,
+            }))
+        );
+        let rules = SchematicRules {
+            pad_ragged: true,
+            ..SchematicRules::default()
+        };
+        let schematic = Schematic::with_rules(data, rules).expect("rows are padded");
+        assert_eq!(schematic.grid.to_string(), "467.\n..*.\n35..\n");
+        assert_eq!(schematic.sum(), Ok(467 + 35));
+    }
 }
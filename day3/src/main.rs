@@ -1,25 +1,65 @@
-use day3::Schematic;
+use anyhow::{bail, Context};
+use day3::error::SchematicError;
+use day3::rules::SchematicRules;
+use day3::stats::SchematicStats;
+use day3::validate::validate_input;
+use day3::{render, Schematic};
 
 const INPUT: &str = include_str!("input.txt");
 
-fn solve_part1(input: &str) -> u32 {
-    let schematic = Schematic::new(input);
+fn solve_part1(input: &str) -> Result<u64, SchematicError> {
+    let schematic = Schematic::new(input)?;
     schematic.sum()
 }
 
-fn solve_part2(input: &str) -> u64 {
-    let schematic = Schematic::new(input);
-    schematic
-        .find_gears()
-        .into_iter()
-        .map(|gear| gear.gear_ratio())
-        .sum()
+fn solve_part2(input: &str) -> Result<u64, SchematicError> {
+    let schematic = Schematic::new(input)?;
+    let total = schematic.gears().try_fold(0u64, |acc, gear| {
+        acc.checked_add(gear.gear_ratio()?)
+            .ok_or(SchematicError::Overflow)
+    });
+    total
 }
 
 fn main() -> anyhow::Result<()> {
-    let part1 = solve_part1(INPUT);
+    let mut renderer = None;
+    let mut stats = None;
+    for diagnostic in validate_input(INPUT, &SchematicRules::default()) {
+        eprintln!("warning: {}", diagnostic);
+    }
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--render" => renderer = Some(args.next().context("--render needs ansi or svg")?),
+            "--stats" => stats = Some(args.next().context("--stats needs table or json")?),
+            other => bail!("unknown argument {other}"),
+        }
+    }
+
+    if let Some(renderer) = renderer {
+        let schematic = Schematic::new(INPUT)?;
+        match renderer.as_str() {
+            "ansi" => print!("{}", render::ansi(&schematic)),
+            "svg" => print!("{}", render::svg(&schematic)),
+            other => bail!("unknown renderer {other}, expected ansi or svg"),
+        }
+        return Ok(());
+    }
+
+    if let Some(format) = stats {
+        let stats = SchematicStats::new(&Schematic::new(INPUT)?, 10)?;
+        match format.as_str() {
+            "table" => print!("{}", stats),
+            "json" => println!("{}", stats.to_json()),
+            other => bail!("unknown stats format {other}, expected table or json"),
+        }
+        return Ok(());
+    }
+
+    let part1 = solve_part1(INPUT)?;
     println!("part1: {}", part1);
-    let part2 = solve_part2(INPUT);
+    let part2 = solve_part2(INPUT)?;
     println!("part2: {}", part2);
     Ok(())
 }
@@ -43,11 +83,11 @@ mod test {
     #[test]
     fn test_solve_part1() {
         let solution = solve_part1(INPUT);
-        assert_eq!(solution, 4361);
+        assert_eq!(solution, Ok(4361));
     }
     #[test]
     fn test_solve_part2() {
         let solution = solve_part2(INPUT);
-        assert_eq!(solution, 467835);
+        assert_eq!(solution, Ok(467835));
     }
 }
@@ -0,0 +1,169 @@
+//! Processing a schematic in bands of rows on scoped threads.
+//!
+//! Numbers never span rows, so splitting on row boundaries can't cut one in half, and every
+//! band reads its numbers independently. A symbol on the first or last row of a band touches
+//! numbers in the neighbouring band, so joining symbols to numbers waits until the whole number
+//! index is built and then reads across the boundary. A part touching symbols in two bands is
+//! found by both, and merging the bands in order keeps the first.
+
+use std::{collections::HashSet, ops::Range, thread};
+
+use crate::{error::SchematicError, rules::SchematicRules, scan::Numbers, Gear, Schematic};
+
+/// Runs `work` on up to `threads` bands of consecutive rows at once, returning the results in
+/// row order.
+fn in_bands<T: Send>(
+    height: usize,
+    threads: usize,
+    work: impl Fn(Range<usize>) -> T + Sync,
+) -> Vec<T> {
+    let rows_per_band = height.div_ceil(threads.max(1)).max(1);
+    let work = &work;
+    thread::scope(|scope| {
+        let bands = (0..height)
+            .step_by(rows_per_band)
+            .map(|first| scope.spawn(move || work(first..(first + rows_per_band).min(height))))
+            .collect::<Vec<_>>();
+        bands
+            .into_iter()
+            .map(|band| band.join().expect("band threads don't panic"))
+            .collect()
+    })
+}
+
+impl Schematic {
+    /// Like `with_rules`, but split across `threads` threads (at least one). The result is the
+    /// same, down to the order of the parts, as is the error if more than one number is too
+    /// large.
+    pub fn with_rules_parallel(
+        input: &str,
+        rules: SchematicRules,
+        threads: usize,
+    ) -> Result<Self, SchematicError> {
+        let grid = Self::read_grid(input, &rules)?;
+
+        let mut numbers = Numbers::empty(&grid);
+        let bands = in_bands(grid.height(), threads, |rows| {
+            rows.map(|y| Numbers::read(&grid, y, 0, grid.width()))
+                .collect::<Result<Vec<_>, _>>()
+        });
+        for band in bands {
+            for part in band?.into_iter().flatten() {
+                numbers.insert(part);
+            }
+        }
+
+        let bands = in_bands(grid.height(), threads, |rows| {
+            let mut seen = HashSet::new();
+            let mut parts = Vec::new();
+            for y in rows {
+                for x in 0..grid.width() {
+                    if rules.is_symbol(grid[(x, y)]) {
+                        parts.extend(
+                            numbers
+                                .around((x, y))
+                                .into_iter()
+                                .filter(|part| seen.insert(*part)),
+                        );
+                    }
+                }
+            }
+            parts
+        });
+        let mut is_part = HashSet::new();
+        let mut parts = Vec::new();
+        for part in bands.into_iter().flatten() {
+            if is_part.insert(part) {
+                parts.push(part);
+            }
+        }
+
+        Ok(Self {
+            grid,
+            numbers,
+            parts,
+            is_part,
+            rules,
+        })
+    }
+
+    /// Like `find_gears`, but split across `threads` threads.
+    pub fn find_gears_parallel(&self, threads: usize) -> Vec<Gear> {
+        in_bands(self.grid.height(), threads, |rows| {
+            rows.flat_map(|y| (0..self.grid.width()).filter_map(move |x| self.gear_at((x, y))))
+                .collect::<Vec<_>>()
+        })
+        .concat()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::XorShift;
+
+    /// A `width` by `height` schematic of mostly numbers and blanks, the same on every run.
+    fn generate(width: usize, height: usize) -> String {
+        let mut rng = XorShift(0x9e37_79b9_7f4a_7c15);
+        let cells = ['.', '.', '.', '.', '1', '2', '3', '7', '9', '*', '*', '#'];
+        let mut input = String::new();
+        for _ in 0..height {
+            for _ in 0..width {
+                input.push(cells[rng.below(cells.len())]);
+            }
+            input.push('\n');
+        }
+        input
+    }
+
+    fn assert_same(sequential: &Schematic, parallel: &Schematic, threads: usize) {
+        assert_eq!(parallel.grid, sequential.grid);
+        assert_eq!(parallel.numbers, sequential.numbers);
+        assert_eq!(parallel.parts, sequential.parts, "{threads} threads");
+        assert_eq!(parallel.is_part, sequential.is_part);
+        assert_eq!(
+            parallel.find_gears_parallel(threads),
+            sequential.find_gears(),
+            "{threads} threads"
+        );
+    }
+
+    #[test]
+    fn parallel_matches_sequential() {
+        for (width, height) in [(10, 10), (37, 53), (1, 5), (5, 1)] {
+            let input = generate(width, height);
+            let sequential =
+                Schematic::with_rules(&input, SchematicRules::default()).expect("numbers fit");
+            for threads in [0, 1, 2, 3, 4, 7, 16, 100] {
+                let parallel =
+                    Schematic::with_rules_parallel(&input, SchematicRules::default(), threads)
+                        .expect("numbers fit");
+                assert_same(&sequential, &parallel, threads);
+            }
+        }
+    }
+
+    #[test]
+    fn parallel_reports_the_first_error() {
+        let input = "1.\n..\n99999999999999999999\n..\n99999999999999999998\n";
+        let rules = SchematicRules {
+            pad_ragged: true,
+            ..SchematicRules::default()
+        };
+        let sequential = Schematic::with_rules(input, rules.clone()).map(|_| ());
+        assert!(matches!(
+            sequential,
+            Err(SchematicError::NumberTooLarge { y: 2, .. })
+        ));
+        for threads in 1..=5 {
+            let parallel = Schematic::with_rules_parallel(input, rules.clone(), threads);
+            assert_eq!(parallel.map(|_| ()), sequential, "{threads} threads");
+        }
+        assert!(
+            Schematic::with_rules_parallel("", SchematicRules::default(), 4)
+                .expect("nothing to read")
+                .find_gears_parallel(4)
+                .is_empty()
+        );
+    }
+}
@@ -0,0 +1,205 @@
+//! Pictures of what a `Schematic` decided: which numbers are parts, which are not, and which
+//! symbols are gears.
+
+use std::{collections::HashSet, fmt::Write};
+
+use crate::{BoundingBox, Schematic};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Cell {
+    Blank,
+    Part,
+    /// A number touching no symbol.
+    Orphan,
+    Symbol,
+    Gear,
+    /// Anything the rules don't treat as a symbol.
+    Other,
+}
+
+impl Cell {
+    fn ansi(self) -> Option<&'static str> {
+        match self {
+            Cell::Part => Some("\x1b[32m"),
+            Cell::Orphan => Some("\x1b[31m"),
+            Cell::Symbol => Some("\x1b[33m"),
+            Cell::Gear => Some("\x1b[1;35m"),
+            Cell::Blank | Cell::Other => None,
+        }
+    }
+
+    fn svg(self) -> &'static str {
+        match self {
+            Cell::Part => "green",
+            Cell::Orphan => "red",
+            Cell::Symbol => "goldenrod",
+            Cell::Gear => "purple",
+            Cell::Blank | Cell::Other => "gray",
+        }
+    }
+}
+
+fn classify(schematic: &Schematic) -> impl Fn((usize, usize), char) -> Cell + '_ {
+    let gears = schematic
+        .gears()
+        .map(|gear| gear.position)
+        .collect::<HashSet<_>>();
+    move |pos, val| match schematic.numbers.at(pos) {
+        Some(number) if schematic.is_part.contains(&number) => Cell::Part,
+        Some(_) => Cell::Orphan,
+        None if gears.contains(&pos) => Cell::Gear,
+        None if schematic.rules.is_symbol(val) => Cell::Symbol,
+        None if val == schematic.rules.blank => Cell::Blank,
+        None => Cell::Other,
+    }
+}
+
+/// The grid with parts in green, orphan numbers in red, symbols in yellow and gears in bold
+/// magenta, for printing to a terminal.
+pub fn ansi(schematic: &Schematic) -> String {
+    let classify = classify(schematic);
+    let mut out = String::new();
+    for (y, row) in schematic.grid.rows().enumerate() {
+        let mut current = None;
+        for (x, &val) in row.iter().enumerate() {
+            let colour = classify((x, y), val).ansi();
+            if colour != current {
+                // Reset first so that a gear's bold doesn't carry over to the next colour.
+                if current.is_some() {
+                    out.push_str("\x1b[0m");
+                }
+                if let Some(colour) = colour {
+                    out.push_str(colour);
+                }
+                current = colour;
+            }
+            out.push(val);
+        }
+        if current.is_some() {
+            out.push_str("\x1b[0m");
+        }
+        out.push('\n');
+    }
+    out
+}
+
+const CELL: usize = 12;
+
+fn centre(bbox: &BoundingBox) -> (usize, usize) {
+    let ((x1, y1), (x2, y2)) = (bbox.topleft(), bbox.bottomright());
+    ((x1 + x2 + 1) * CELL / 2, (y1 + y2 + 1) * CELL / 2)
+}
+
+fn escape(val: char) -> String {
+    match val {
+        '&' => "&amp;".to_string(),
+        '<' => "&lt;".to_string(),
+        '>' => "&gt;".to_string(),
+        val => val.to_string(),
+    }
+}
+
+/// An SVG of the grid coloured as in `ansi`, with a box around every part and a line from
+/// every gear to each of its parts.
+pub fn svg(schematic: &Schematic) -> String {
+    let classify = classify(schematic);
+    let (width, height) = (
+        schematic.grid.width() * CELL,
+        schematic.grid.height() * CELL,
+    );
+    let mut out = String::new();
+    writeln!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}" font-family="monospace" font-size="{CELL}">"#
+    )
+    .expect("writing to a String can't fail");
+    for ((x, y), &val) in schematic.grid.iter() {
+        let cell = classify((x, y), val);
+        if cell == Cell::Blank {
+            continue;
+        }
+        writeln!(
+            out,
+            r#"<text x="{}" y="{}" fill="{}" text-anchor="middle" dominant-baseline="central">{}</text>"#,
+            x * CELL + CELL / 2,
+            y * CELL + CELL / 2,
+            cell.svg(),
+            escape(val)
+        )
+        .expect("writing to a String can't fail");
+    }
+    for part in &schematic.parts {
+        let (x, y) = part.location.topleft();
+        let (x2, y2) = part.location.bottomright();
+        writeln!(
+            out,
+            r#"<rect x="{}" y="{}" width="{}" height="{}" fill="none" stroke="green"/>"#,
+            x * CELL,
+            y * CELL,
+            (x2 - x + 1) * CELL,
+            (y2 - y + 1) * CELL
+        )
+        .expect("writing to a String can't fail");
+    }
+    for gear in schematic.gears() {
+        let (x1, y1) = centre(&BoundingBox::new(gear.position, gear.position));
+        for part in &gear.adjacent_parts {
+            let (x2, y2) = centre(&part.location);
+            writeln!(
+                out,
+                r#"<line x1="{x1}" y1="{y1}" x2="{x2}" y2="{y2}" stroke="purple"/>"#
+            )
+            .expect("writing to a String can't fail");
+        }
+    }
+    out.push_str("</svg>\n");
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const DATA: &str = "\
+12.3
+.*..
+4...
+";
+
+    #[test]
+    fn ansi_colours_each_kind_of_cell() {
+        let rendered = ansi(&Schematic::new(DATA).expect("numbers fit"));
+        let lines = rendered.lines().collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            vec![
+                "\x1b[32m12\x1b[0m.\x1b[31m3\x1b[0m",
+                ".\x1b[1;35m*\x1b[0m..",
+                "\x1b[32m4\x1b[0m...",
+            ]
+        );
+    }
+
+    #[test]
+    fn ansi_resets_between_colours() {
+        let rendered = ansi(&Schematic::new("1*2\n...\n").expect("numbers fit"));
+        assert_eq!(
+            rendered.lines().next(),
+            Some("\x1b[32m1\x1b[0m\x1b[1;35m*\x1b[0m\x1b[32m2\x1b[0m")
+        );
+    }
+
+    #[test]
+    fn svg_boxes_parts_and_links_gears() {
+        let rendered = svg(&Schematic::new(DATA).expect("numbers fit"));
+        assert!(rendered.starts_with("<svg "), "{rendered}");
+        assert!(rendered.ends_with("</svg>\n"), "{rendered}");
+        assert_eq!(rendered.matches("<rect ").count(), 2);
+        assert!(rendered.contains(r#"<rect x="0" y="0" width="24" height="12""#));
+        assert_eq!(rendered.matches("<line ").count(), 2);
+        assert!(rendered.contains(r#"<line x1="18" y1="18" x2="12" y2="6""#));
+        assert!(
+            rendered.contains(r#"fill="red" text-anchor="middle" dominant-baseline="central">3<"#)
+        );
+    }
+}
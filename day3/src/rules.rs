@@ -0,0 +1,67 @@
+use std::ops::RangeInclusive;
+
+/// Which characters of a schematic count as symbols.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Symbols {
+    /// Anything that is neither the blank character nor an ASCII digit.
+    Any,
+    OneOf(Vec<char>),
+}
+
+/// How many distinct parts a gear candidate must touch to be a gear.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PartCount {
+    Exactly(usize),
+    AtLeast(usize),
+    Between(RangeInclusive<usize>),
+}
+
+impl PartCount {
+    pub fn allows(&self, count: usize) -> bool {
+        match self {
+            PartCount::Exactly(expected) => count == *expected,
+            PartCount::AtLeast(minimum) => count >= *minimum,
+            PartCount::Between(range) => range.contains(&count),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchematicRules {
+    /// Empty cells.
+    pub blank: char,
+    /// Whether rows shorter than the longest are filled out with blanks rather than rejected.
+    pub pad_ragged: bool,
+    pub symbols: Symbols,
+    /// Symbols that become gears when they touch the right number of parts.
+    pub gear_symbols: Vec<char>,
+    pub gear_parts: PartCount,
+}
+
+impl SchematicRules {
+    pub fn is_symbol(&self, val: char) -> bool {
+        if val == self.blank || val.is_ascii_digit() {
+            return false;
+        }
+        match &self.symbols {
+            Symbols::Any => true,
+            Symbols::OneOf(symbols) => symbols.contains(&val),
+        }
+    }
+
+    pub fn is_gear_candidate(&self, val: char) -> bool {
+        self.is_symbol(val) && self.gear_symbols.contains(&val)
+    }
+}
+
+impl Default for SchematicRules {
+    fn default() -> Self {
+        Self {
+            blank: '.',
+            pad_ragged: false,
+            symbols: Symbols::Any,
+            gear_symbols: vec!['*'],
+            gear_parts: PartCount::Exactly(2),
+        }
+    }
+}
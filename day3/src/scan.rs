@@ -0,0 +1,235 @@
+//! Single-pass extraction of the numbers in a schematic, and an index from cells back to them.
+
+use std::collections::BTreeMap;
+
+use crate::{error::SchematicError, grid::Grid, Part};
+
+/// Every run of digits in a grid, found in a single pass over each row, along with which run
+/// (if any) covers each cell.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Numbers {
+    /// For each row, its numbers keyed by the column they start in.
+    rows: Vec<BTreeMap<usize, Part>>,
+    /// For each cell, the column the number covering it starts in.
+    starts: Grid<Option<usize>>,
+}
+
+impl Numbers {
+    /// Fails if any number is too large for a `u64`.
+    pub fn new(grid: &Grid<char>) -> Result<Self, SchematicError> {
+        let mut numbers = Self::empty(grid);
+        for y in 0..grid.height() {
+            for part in Self::read(grid, y, 0, grid.width())? {
+                numbers.insert(part);
+            }
+        }
+        Ok(numbers)
+    }
+
+    /// Every number in row `y` between columns `from` and `to` (exclusive), which must not cut
+    /// through a run of digits, left to right.
+    pub(crate) fn read(
+        grid: &Grid<char>,
+        y: usize,
+        from: usize,
+        to: usize,
+    ) -> Result<Vec<Part>, SchematicError> {
+        let row = grid.row(y).expect("scanned rows are on the grid");
+        let mut found = Vec::new();
+        let mut start = None;
+        // One step past the end of the span closes a number running up to it.
+        for x in from..=to {
+            let is_digit = x < to && row[x].is_ascii_digit();
+            match (start, is_digit) {
+                (None, true) => start = Some(x),
+                (Some(first), false) => {
+                    found.push(Part::parse(&row[first..x], (first, y))?);
+                    start = None;
+                }
+                _ => {}
+            }
+        }
+        Ok(found)
+    }
+
+    /// An index of the right size for `grid`, holding no numbers.
+    pub(crate) fn empty(grid: &Grid<char>) -> Self {
+        Self {
+            rows: vec![BTreeMap::new(); grid.height()],
+            starts: Grid::filled(grid.width(), grid.height(), None),
+        }
+    }
+
+    pub(crate) fn insert(&mut self, part: Part) {
+        let ((x1, y), (x2, _)) = (part.location.topleft(), part.location.bottomright());
+        for column in x1..=x2 {
+            self.starts[(column, y)] = Some(x1);
+        }
+        self.rows[y].insert(x1, part);
+    }
+
+    fn remove(&mut self, part: Part) {
+        let ((x1, y), (x2, _)) = (part.location.topleft(), part.location.bottomright());
+        for column in x1..=x2 {
+            self.starts[(column, y)] = None;
+        }
+        self.rows[y].remove(&x1);
+    }
+
+    /// Re-reads the numbers around `(x, y)` after that cell of `grid` has changed. Returns the
+    /// numbers removed and the numbers added; an unchanged number may appear in both. Nothing
+    /// changes if a number is too large.
+    pub(crate) fn rescan(
+        &mut self,
+        grid: &Grid<char>,
+        (x, y): (usize, usize),
+    ) -> Result<Diff, SchematicError> {
+        let (mut from, mut to) = (x, x + 1);
+        let mut removed = Vec::new();
+        for column in x.saturating_sub(1)..=x + 1 {
+            if let Some(part) = self.at((column, y)) {
+                if !removed.contains(&part) {
+                    from = from.min(part.location.topleft().0);
+                    to = to.max(part.location.bottomright().0 + 1);
+                    removed.push(part);
+                }
+            }
+        }
+        let added = Self::read(grid, y, from, to)?;
+        for &part in &removed {
+            self.remove(part);
+        }
+        for &part in &added {
+            self.insert(part);
+        }
+        Ok(Diff { removed, added })
+    }
+
+    pub fn at(&self, (x, y): (usize, usize)) -> Option<Part> {
+        let start = (*self.starts.get((x, y))?)?;
+        Some(self.rows[y][&start])
+    }
+
+    /// The distinct numbers touching `coord`, in the order its neighbours are visited.
+    pub fn around(&self, coord: (usize, usize)) -> Vec<Part> {
+        let mut found = Vec::with_capacity(4);
+        for part in self
+            .starts
+            .neighbours8(coord)
+            .filter_map(|adj| self.at(adj))
+        {
+            if !found.contains(&part) {
+                found.push(part);
+            }
+        }
+        found
+    }
+
+    /// Every number in reading order.
+    pub fn iter(&self) -> impl Iterator<Item = &Part> {
+        self.rows.iter().flat_map(BTreeMap::values)
+    }
+}
+
+/// The numbers, or parts, that a change to a schematic removed and added.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Diff {
+    pub removed: Vec<Part>,
+    pub added: Vec<Part>,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn numbers_in_reading_order() {
+        let grid: Grid<char> = "12..3\n.45.6\n7...."
+            .parse()
+            .expect("rows are the same length");
+        let found = Numbers::new(&grid)
+            .expect("numbers are small")
+            .iter()
+            .map(|part| {
+                (
+                    part.id,
+                    part.location.topleft(),
+                    part.location.bottomright(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            found,
+            vec![
+                (12, (0, 0), (1, 0)),
+                (3, (4, 0), (4, 0)),
+                (45, (1, 1), (2, 1)),
+                (6, (4, 1), (4, 1)),
+                (7, (0, 2), (0, 2)),
+            ]
+        );
+    }
+
+    #[test]
+    fn index_finds_each_number_once() {
+        let grid: Grid<char> = "123.\n.*..\n..45"
+            .parse()
+            .expect("rows are the same length");
+        let numbers = Numbers::new(&grid).expect("numbers are small");
+        let ids = |parts: Vec<Part>| parts.iter().map(|part| part.id).collect::<Vec<_>>();
+        assert_eq!(numbers.at((2, 0)).map(|part| part.id), Some(123));
+        assert_eq!(numbers.at((3, 0)), None);
+        assert_eq!(ids(numbers.around((1, 1))), vec![123, 45]);
+        assert_eq!(ids(numbers.around((3, 0))), vec![123]);
+        assert!(numbers.around((0, 2)).is_empty());
+    }
+
+    #[test]
+    fn rescan_splits_and_joins_numbers() {
+        let mut grid: Grid<char> = "12.34\n.....".parse().expect("rows are the same length");
+        let mut numbers = Numbers::new(&grid).expect("numbers are small");
+        grid[(2, 0)] = '5';
+        let diff = numbers.rescan(&grid, (2, 0)).expect("numbers are small");
+        assert_eq!(diff.removed.len(), 2);
+        assert_eq!(
+            diff.added.iter().map(|part| part.id).collect::<Vec<_>>(),
+            vec![12534]
+        );
+        assert_eq!(Ok(&numbers), Numbers::new(&grid).as_ref());
+        grid[(1, 0)] = '.';
+        numbers.rescan(&grid, (1, 0)).expect("numbers are small");
+        assert_eq!(Ok(&numbers), Numbers::new(&grid).as_ref());
+    }
+
+    #[test]
+    fn only_ascii_digits_make_numbers() {
+        let grid: Grid<char> = "1٣2.²".parse().expect("one row");
+        let ids = Numbers::new(&grid)
+            .expect("numbers are small")
+            .iter()
+            .map(|part| part.id)
+            .collect::<Vec<_>>();
+        assert_eq!(ids, vec![1, 2]);
+    }
+
+    #[test]
+    fn too_large_numbers_are_errors() {
+        let grid: Grid<char> = "18446744073709551615.\n.18446744073709551616"
+            .parse()
+            .expect("rows are the same length");
+        assert_eq!(
+            Numbers::new(&grid),
+            Err(SchematicError::NumberTooLarge {
+                x: 1,
+                y: 1,
+                digits: "18446744073709551616".to_string(),
+            })
+        );
+        let mut grid: Grid<char> = "1844674407370955161.6".parse().expect("one row");
+        let mut numbers = Numbers::new(&grid).expect("numbers are small");
+        let before = numbers.clone();
+        grid[(19, 0)] = '5';
+        assert!(numbers.rescan(&grid, (19, 0)).is_err());
+        assert_eq!(numbers, before);
+    }
+}
@@ -0,0 +1,196 @@
+//! Aggregates over the symbols, parts and gears of a schematic.
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt::Display,
+};
+
+use crate::{error::SchematicError, Schematic};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SymbolStats {
+    pub symbol: char,
+    /// How many times the symbol appears.
+    pub count: usize,
+    /// How many distinct parts touch at least one copy of the symbol.
+    pub parts: usize,
+    /// The sum of the ids of those parts.
+    pub part_sum: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchematicStats {
+    /// One entry per distinct symbol, in character order.
+    pub symbols: Vec<SymbolStats>,
+    /// How many symbols touch each number of parts.
+    pub adjacent_parts: BTreeMap<usize, usize>,
+    /// The positions and ratios of the gears with the largest ratios, largest first.
+    pub top_gear_ratios: Vec<((usize, usize), u64)>,
+}
+
+impl SchematicStats {
+    /// Keeps the `top` largest gear ratios. Fails if a part sum or gear ratio overflows.
+    pub fn new(schematic: &Schematic, top: usize) -> Result<Self, SchematicError> {
+        let mut by_symbol = BTreeMap::<char, (usize, HashSet<_>)>::new();
+        let mut adjacent_parts = BTreeMap::new();
+        for (position, symbol) in schematic.symbols() {
+            let parts = schematic.numbers.around(position);
+            *adjacent_parts.entry(parts.len()).or_default() += 1;
+            let (count, touched) = by_symbol.entry(symbol).or_default();
+            *count += 1;
+            touched.extend(parts);
+        }
+        let symbols = by_symbol
+            .into_iter()
+            .map(|(symbol, (count, parts))| {
+                let part_sum = parts
+                    .iter()
+                    .try_fold(0u64, |acc, part| acc.checked_add(part.id))
+                    .ok_or(SchematicError::Overflow)?;
+                Ok(SymbolStats {
+                    symbol,
+                    count,
+                    parts: parts.len(),
+                    part_sum,
+                })
+            })
+            .collect::<Result<Vec<_>, SchematicError>>()?;
+
+        let mut top_gear_ratios = schematic
+            .gears()
+            .map(|gear| Ok((gear.position(), gear.gear_ratio()?)))
+            .collect::<Result<Vec<_>, SchematicError>>()?;
+        // Ties go to the gear found first.
+        top_gear_ratios.sort_by(|(a, ratio_a), (b, ratio_b)| {
+            ratio_b.cmp(ratio_a).then((a.1, a.0).cmp(&(b.1, b.0)))
+        });
+        top_gear_ratios.truncate(top);
+
+        Ok(Self {
+            symbols,
+            adjacent_parts,
+            top_gear_ratios,
+        })
+    }
+
+    pub fn to_json(&self) -> String {
+        let symbols = self
+            .symbols
+            .iter()
+            .map(|stats| {
+                format!(
+                    r#"{{"symbol": {}, "count": {}, "parts": {}, "part_sum": {}}}"#,
+                    json_string(stats.symbol),
+                    stats.count,
+                    stats.parts,
+                    stats.part_sum
+                )
+            })
+            .collect::<Vec<_>>();
+        let adjacent_parts = self
+            .adjacent_parts
+            .iter()
+            .map(|(parts, symbols)| format!(r#""{parts}": {symbols}"#))
+            .collect::<Vec<_>>();
+        let top_gear_ratios = self
+            .top_gear_ratios
+            .iter()
+            .map(|((x, y), ratio)| format!(r#"{{"x": {x}, "y": {y}, "ratio": {ratio}}}"#))
+            .collect::<Vec<_>>();
+        format!(
+            r#"{{"symbols": [{}], "adjacent_parts": {{{}}}, "top_gear_ratios": [{}]}}"#,
+            symbols.join(", "),
+            adjacent_parts.join(", "),
+            top_gear_ratios.join(", ")
+        )
+    }
+}
+
+fn json_string(val: char) -> String {
+    let mut out = String::from('"');
+    match val {
+        '"' => out.push_str("\\\""),
+        '\\' => out.push_str("\\\\"),
+        val if val.is_control() => out.push_str(&format!("\\u{:04x}", val as u32)),
+        val => out.push(val),
+    }
+    out.push('"');
+    out
+}
+
+impl Display for SchematicStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "{:<8}{:>8}{:>8}{:>12}",
+            "symbol", "count", "parts", "part sum"
+        )?;
+        for stats in &self.symbols {
+            writeln!(
+                f,
+                "{:<8}{:>8}{:>8}{:>12}",
+                stats.symbol, stats.count, stats.parts, stats.part_sum
+            )?;
+        }
+        writeln!(f)?;
+        writeln!(f, "{:<16}{:>8}", "adjacent parts", "symbols")?;
+        for (parts, symbols) in &self.adjacent_parts {
+            writeln!(f, "{:<16}{:>8}", parts, symbols)?;
+        }
+        writeln!(f)?;
+        writeln!(f, "{:<16}{:>20}", "gear", "ratio")?;
+        for ((x, y), ratio) in &self.top_gear_ratios {
+            writeln!(f, "{:<16}{:>20}", format!("({x}, {y})"), ratio)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::EXAMPLE;
+
+    #[test]
+    fn stats_for_example() {
+        let schematic = Schematic::new(EXAMPLE).expect("numbers fit");
+        let stats = SchematicStats::new(&schematic, 1).expect("totals fit");
+        assert_eq!(
+            stats.symbols[0],
+            SymbolStats {
+                symbol: '#',
+                count: 1,
+                parts: 1,
+                part_sum: 633,
+            }
+        );
+        let star = &stats.symbols[2];
+        assert_eq!((star.symbol, star.count, star.parts), ('*', 3, 5));
+        assert_eq!(star.part_sum, 467 + 35 + 617 + 755 + 598);
+        assert_eq!(
+            stats.adjacent_parts,
+            [(1, 4), (2, 2)].into_iter().collect::<BTreeMap<_, _>>()
+        );
+        assert_eq!(stats.top_gear_ratios, vec![((5, 8), 755 * 598)]);
+
+        let table = stats.to_string();
+        assert!(
+            table.contains("*              3       5        2472"),
+            "{table}"
+        );
+        assert!(
+            table.contains("(5, 8)                        451490"),
+            "{table}"
+        );
+    }
+
+    #[test]
+    fn json_report() {
+        let schematic = Schematic::new("1\"2\n").expect("numbers fit");
+        let stats = SchematicStats::new(&schematic, 10).expect("totals fit");
+        assert_eq!(
+            stats.to_json(),
+            r#"{"symbols": [{"symbol": "\"", "count": 1, "parts": 2, "part_sum": 3}], "adjacent_parts": {"2": 1}, "top_gear_ratios": []}"#
+        );
+    }
+}
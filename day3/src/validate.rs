@@ -0,0 +1,160 @@
+use std::fmt::Display;
+
+use crate::{
+    raw_rows,
+    rules::{SchematicRules, Symbols},
+};
+
+/// Problems with the text of a schematic. Coordinates are `(x, y)` in characters, counting from
+/// zero.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InputDiagnostic {
+    /// A row whose length differs from the first row's.
+    Ragged {
+        row: usize,
+        expected: usize,
+        found: usize,
+    },
+    Tab {
+        x: usize,
+        y: usize,
+    },
+    /// A `\r`, usually left behind by CRLF line endings.
+    CarriageReturn {
+        x: usize,
+        y: usize,
+    },
+    /// A character that is neither blank, a digit nor a symbol.
+    Unexpected {
+        x: usize,
+        y: usize,
+        found: char,
+    },
+}
+
+impl Display for InputDiagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Ragged {
+                row,
+                expected,
+                found,
+            } => write!(f, "row {} has {} cells, expected {}", row, found, expected),
+            Self::Tab { x, y } => write!(f, "tab at ({}, {})", x, y),
+            Self::CarriageReturn { x, y } => write!(f, "carriage return at ({}, {})", x, y),
+            Self::Unexpected { x, y, found } => {
+                write!(f, "unexpected {:?} at ({}, {})", found, x, y)
+            }
+        }
+    }
+}
+
+/// Checks that `input` is a rectangle of blanks, digits and symbols under `rules`. When the
+/// rules accept any symbol, only ASCII punctuation is expected.
+pub fn validate_input(input: &str, rules: &SchematicRules) -> Vec<InputDiagnostic> {
+    let mut diagnostics = Vec::new();
+    let mut expected = None;
+    // Rows keep any '\r' so it can be reported.
+    for (y, row) in raw_rows(input).into_iter().enumerate() {
+        for (x, val) in row.chars().enumerate() {
+            let diagnostic = match val {
+                '\t' => InputDiagnostic::Tab { x, y },
+                '\r' => InputDiagnostic::CarriageReturn { x, y },
+                val if val == rules.blank || val.is_ascii_digit() => continue,
+                val if expected_symbol(rules, val) => continue,
+                found => InputDiagnostic::Unexpected { x, y, found },
+            };
+            diagnostics.push(diagnostic);
+        }
+        // Measured the way `Schematic` reads rows, without a trailing '\r'.
+        let found = row.strip_suffix('\r').unwrap_or(row).chars().count();
+        match expected {
+            None => expected = Some(found),
+            Some(expected) if expected != found => diagnostics.push(InputDiagnostic::Ragged {
+                row: y,
+                expected,
+                found,
+            }),
+            Some(_) => {}
+        }
+    }
+    diagnostics
+}
+
+fn expected_symbol(rules: &SchematicRules, val: char) -> bool {
+    match &rules.symbols {
+        Symbols::Any => val.is_ascii_punctuation(),
+        Symbols::OneOf(symbols) => symbols.contains(&val),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn clean_input_has_no_diagnostics() {
+        let rules = SchematicRules::default();
+        assert!(validate_input("467..\n..*#.\n", &rules).is_empty());
+        assert!(validate_input("467..\n..*#.\n\n\n", &rules).is_empty());
+        assert!(validate_input("", &rules).is_empty());
+    }
+
+    #[test]
+    fn blank_crlf_lines_at_the_end_are_not_rows() {
+        let rules = SchematicRules::default();
+        let input = "1*\r\n\r\n";
+        assert_eq!(
+            validate_input(input, &rules),
+            vec![InputDiagnostic::CarriageReturn { x: 2, y: 0 }]
+        );
+        let schematic = crate::Schematic::new(input).expect("numbers fit");
+        assert_eq!(schematic.grid.height(), 1);
+        assert_eq!(schematic.grid.width(), 2);
+    }
+
+    #[test]
+    fn reports_each_problem_with_coordinates() {
+        let rules = SchematicRules::default();
+        let input = "12.*\r\n.\t..\r\n..\n3a.é\n";
+        assert_eq!(
+            validate_input(input, &rules),
+            vec![
+                InputDiagnostic::CarriageReturn { x: 4, y: 0 },
+                InputDiagnostic::Tab { x: 1, y: 1 },
+                InputDiagnostic::CarriageReturn { x: 4, y: 1 },
+                InputDiagnostic::Ragged {
+                    row: 2,
+                    expected: 4,
+                    found: 2,
+                },
+                InputDiagnostic::Unexpected {
+                    x: 1,
+                    y: 3,
+                    found: 'a',
+                },
+                InputDiagnostic::Unexpected {
+                    x: 3,
+                    y: 3,
+                    found: 'é',
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn symbols_follow_the_rules() {
+        let rules = SchematicRules {
+            symbols: Symbols::OneOf(vec!['*', 'x']),
+            ..SchematicRules::default()
+        };
+        assert_eq!(
+            validate_input("1x*#", &rules),
+            vec![InputDiagnostic::Unexpected {
+                x: 3,
+                y: 0,
+                found: '#',
+            }]
+        );
+    }
+}